@@ -1,4 +1,3 @@
-use std::thread;
 use std::time::Duration;
 use visca::{Camera, PanTiltValue, Result};
 
@@ -8,10 +7,10 @@ fn main() -> Result<()> {
     let pt = PanTiltValue { pan: 80, tilt: 50 };
     camera.pan_tilt().set_absolute(pt)?;
 
-    thread::sleep(Duration::from_secs(2));
-
-    camera.pan_tilt().get().and_then(|pan_tilt| {
-        println!("{:?}", pan_tilt);
-        Ok(())
-    })
+    camera
+        .pan_tilt()
+        .wait_for_position(pt, 0, Duration::from_secs(10))
+        .map(|settled| {
+            println!("{:?}", settled.position);
+        })
 }
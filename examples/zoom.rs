@@ -1,22 +1,20 @@
-use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use visca::{Camera, Result};
 
 fn main() -> Result<()> {
     let mut camera = Camera::open("/dev/cu.usbserial-AM00QCCD")?;
     let want = 444;
 
-    let now = Instant::now();
     camera.zoom().set(want)?;
 
-    loop {
-        let zoom = camera.zoom().get()?;
-        if zoom == want {
-            println!("{}ms", now.elapsed().as_millis());
-            break;
-        }
-        thread::sleep(Duration::from_millis(50));
-    }
+    let settled = camera
+        .zoom()
+        .wait_for_position(want, 0, Duration::from_secs(5))?;
+    println!(
+        "{} after {}ms",
+        settled.position,
+        settled.elapsed.as_millis()
+    );
 
     Ok(())
 }
@@ -0,0 +1,71 @@
+use crate::commands::PanTiltValue;
+use crate::{Camera, Result};
+use std::time::Duration;
+
+/// The longest a zoom at a given speed may take, which covers even the slowest lens.
+const ZOOM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A single command for a camera, as cue lists and scripts give them.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    Recall {
+        preset: u8,
+        speed: Option<u8>,
+    },
+    Store(u8),
+    /// An absolute pan-tilt move, at the given pan and tilt speeds if any.
+    Move {
+        pan_tilt: PanTiltValue,
+        speed: Option<(u8, u8)>,
+    },
+    Home,
+    PanTiltStop,
+    /// A zoom to an absolute position, driven at the given variable speed if any.
+    Zoom {
+        position: u16,
+        speed: Option<u8>,
+    },
+    ZoomTele(u8),
+    ZoomWide(u8),
+    ZoomStop,
+    Focus(u16),
+    FocusAuto(bool),
+    FocusOnePush,
+    FocusStop,
+    Power(bool),
+}
+
+/// Sends an action to a camera, returning once the camera has carried it out.
+pub(crate) fn perform(camera: &mut Camera, action: Action) -> Result<()> {
+    match action {
+        Action::Recall { preset, speed } => {
+            if let Some(speed) = speed {
+                camera.presets().set_recall_speed(speed)?;
+            }
+            camera.presets().recall(preset)
+        }
+        Action::Store(preset) => camera.presets().set(preset),
+        Action::Move { pan_tilt, speed } => match speed {
+            Some((pan_speed, tilt_speed)) => camera
+                .pan_tilt()
+                .set_absolute_at_speed(pan_tilt, pan_speed, tilt_speed),
+            None => camera.pan_tilt().set_absolute(pan_tilt),
+        },
+        Action::Home => camera.pan_tilt().home(),
+        Action::PanTiltStop => camera.pan_tilt().stop(),
+        Action::Zoom { position, speed } => match speed {
+            Some(speed) => camera.zoom().set_at_speed(position, speed, ZOOM_TIMEOUT),
+            None => camera.zoom().set(position),
+        },
+        Action::ZoomTele(speed) => camera.zoom().tele(speed),
+        Action::ZoomWide(speed) => camera.zoom().wide(speed),
+        Action::ZoomStop => camera.zoom().stop(),
+        Action::Focus(pos) => camera.focus().set(pos),
+        Action::FocusAuto(true) => camera.focus().auto(),
+        Action::FocusAuto(false) => camera.focus().manual(),
+        Action::FocusOnePush => camera.focus().one_push(),
+        Action::FocusStop => camera.focus().stop(),
+        Action::Power(true) => camera.power().on(),
+        Action::Power(false) => camera.power().off(),
+    }
+}
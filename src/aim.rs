@@ -0,0 +1,183 @@
+//! Aiming the camera at things it can see.
+//!
+//! Points and rectangles are given in normalized image coordinates: `(0, 0)` is the top
+//! left corner of the picture and `(1, 1)` the bottom right.
+
+use crate::calibration::{Calibration, Degrees};
+use crate::commands::PanTiltValue;
+use crate::lens::{FieldOfView, ZoomTable};
+use crate::{Camera, Error, Result};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn center(&self) -> Point {
+        Point {
+            x: self.x + self.width / 2.0,
+            y: self.y + self.height / 2.0,
+        }
+    }
+}
+
+impl Point {
+    /// Returns the angle between the middle of the picture and this point, for a lens with
+    /// the given field of view.
+    pub fn offset(self, fov: FieldOfView) -> Degrees {
+        let dx = (self.x - 0.5) * 2.0;
+        let dy = (0.5 - self.y) * 2.0;
+
+        Degrees {
+            pan: (dx * (fov.horizontal / 2.0).to_radians().tan())
+                .atan()
+                .to_degrees(),
+            tilt: (dy * (fov.vertical / 2.0).to_radians().tan())
+                .atan()
+                .to_degrees(),
+        }
+    }
+
+    fn in_frame(self) -> bool {
+        (0.0..=1.0).contains(&self.x) && (0.0..=1.0).contains(&self.y)
+    }
+}
+
+impl Camera {
+    /// Turns the camera so that `point` ends up in the middle of the picture. Fails with
+    /// `Error::OutOfRange` if the point lies outside the picture or the camera can't turn
+    /// that far.
+    pub fn center_on(&mut self, point: Point, cal: &Calibration, table: &ZoomTable) -> Result<()> {
+        let target = self.aim_at(point, cal, table)?;
+        self.pan_tilt().set_absolute(target)
+    }
+
+    /// Centers the camera on `rect` and zooms in until it fills the picture, as far as the
+    /// lens allows.
+    pub fn zoom_to(&mut self, rect: Rect, cal: &Calibration, table: &ZoomTable) -> Result<()> {
+        if !(rect.width > 0.0 && rect.height > 0.0) {
+            return Err(Error::OutOfRange);
+        }
+
+        let target = self.aim_at(rect.center(), cal, table)?;
+        let current = self.zoom().get_magnification(table)?;
+        let magnification = current / rect.width.max(rect.height);
+
+        self.pan_tilt().set_absolute(target)?;
+        self.zoom()
+            .set_magnification(magnification.min(table.max_magnification()), table)
+    }
+
+    /// Works out the position that centers a point in the current picture.
+    fn aim_at(
+        &mut self,
+        point: Point,
+        cal: &Calibration,
+        table: &ZoomTable,
+    ) -> Result<PanTiltValue> {
+        if !point.in_frame() {
+            return Err(Error::OutOfRange);
+        }
+
+        let fov = self.zoom().get_fov(table)?;
+        let current = self.pan_tilt().get_degrees(cal)?;
+        let offset = point.offset(fov);
+
+        let degrees = Degrees {
+            pan: current.pan + offset.pan,
+            tilt: current.tilt + offset.tilt,
+        };
+        PanTiltValue::from_degrees(degrees, cal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Model;
+    use crate::testing::{assert_close, camera};
+    use std::time::Duration;
+
+    #[test]
+    fn test_offset() {
+        let fov = FieldOfView {
+            horizontal: 90.0,
+            vertical: 60.0,
+        };
+
+        let middle = Point { x: 0.5, y: 0.5 }.offset(fov);
+        assert_close(middle.pan, 0.0);
+        assert_close(middle.tilt, 0.0);
+
+        let corner = Point { x: 1.0, y: 0.0 }.offset(fov);
+        assert_close(corner.pan, 45.0);
+        assert_close(corner.tilt, 30.0);
+
+        // The picture is flat, so halfway to the edge is less than half the angle.
+        let halfway = Point { x: 0.25, y: 0.5 }.offset(fov);
+        assert_close(halfway.pan, -(0.5f64.atan().to_degrees()));
+    }
+
+    #[test]
+    fn test_center_on() {
+        let mut camera = camera();
+        let (cal, table) = (Model::EVI_D70.calibration, Model::EVI_D70.zoom_table());
+
+        // The wide end spans 48 degrees, so a tenth of the way to the right edge is a
+        // little under 2.6 degrees away.
+        let point = Point { x: 0.55, y: 0.5 };
+        let offset = point.offset(table.fov(0));
+        assert!(offset.pan > 2.5 && offset.pan < 2.6, "{}", offset.pan);
+        assert_close(offset.tilt, 0.0);
+
+        camera.center_on(point, &cal, &table).unwrap();
+        let target = PanTiltValue::from_degrees(offset, &cal).unwrap();
+        let settled = camera
+            .pan_tilt()
+            .wait_for_position(target, 0, Duration::from_secs(5))
+            .unwrap();
+        assert!(settled.reached);
+        assert_eq!(settled.position, PanTiltValue { pan: 34, tilt: 0 });
+    }
+
+    #[test]
+    fn test_center_on_outside_picture() {
+        let mut camera = camera();
+        let (cal, table) = (Model::EVI_D70.calibration, Model::EVI_D70.zoom_table());
+
+        match camera.center_on(Point { x: 1.5, y: 0.5 }, &cal, &table) {
+            Err(Error::OutOfRange) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_zoom_to() {
+        let mut camera = camera();
+        let (cal, table) = (Model::EVI_D70.calibration, Model::EVI_D70.zoom_table());
+
+        let rect = Rect {
+            x: 0.25,
+            y: 0.25,
+            width: 0.5,
+            height: 0.25,
+        };
+        camera.zoom_to(rect, &cal, &table).unwrap();
+
+        let zoom = camera
+            .zoom()
+            .wait_until_stopped(Duration::from_secs(5))
+            .unwrap();
+        assert_close(table.magnification(zoom.position), 2.0);
+    }
+}
@@ -0,0 +1,145 @@
+use std::env;
+use std::process;
+use std::time::Duration;
+use visca::{ErrorKind, Fault, FaultSchedule};
+
+const USAGE: &str = "\
+Usage: visca-sim [OPTIONS]
+
+Runs a simulated camera behind a pseudo-terminal and prints the path of the device that
+VISCA controllers should open.
+
+Options:
+    --address <N>     Camera address to answer to, 1-7 [default: 1]
+    --link <PATH>     Also make the device available through a symlink at PATH
+    --fault <SPEC>    Inject a fault, may be repeated (see below)
+
+Faults are given as KIND@WHEN. KIND is one of drop-ack, delay:<MS>, corrupt, split,
+full-buffer, not-executable, network-change or silence. WHEN is a request number counted
+from zero, every:<N> for every Nth request, or from:<N> for request N and all later ones.
+For example, `--fault drop-ack@every:3 --fault silence@from:100`.";
+
+struct Options {
+    address: u8,
+    link: Option<String>,
+    faults: FaultSchedule,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let opts = match parse_args(&args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("visca-sim: {}\n\n{}", msg, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&opts) {
+        eprintln!("visca-sim: {}", err);
+        process::exit(1);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options {
+        address: 1,
+        link: None,
+        faults: FaultSchedule::new(),
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+
+        match arg.as_str() {
+            "--address" => {
+                opts.address = match value(arg)?.parse() {
+                    Ok(n @ 1..=7) => n,
+                    _ => return Err("address must be between 1 and 7".to_string()),
+                }
+            }
+            "--link" => opts.link = Some(value(arg)?),
+            "--fault" => {
+                let spec = value(arg)?;
+                opts.faults = add_fault(opts.faults, &spec)
+                    .ok_or_else(|| format!("invalid fault: {}", spec))?;
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    Ok(opts)
+}
+
+fn add_fault(schedule: FaultSchedule, spec: &str) -> Option<FaultSchedule> {
+    let (kind, when) = spec.split_once('@')?;
+
+    let fault = match kind {
+        "drop-ack" => Fault::DropAck,
+        "corrupt" => Fault::Corrupt,
+        "split" => Fault::SplitFrames,
+        "full-buffer" => Fault::Error(ErrorKind::FullBuffer),
+        "not-executable" => Fault::Error(ErrorKind::NotExecutable),
+        "network-change" => Fault::NetworkChange,
+        "silence" => Fault::Silence,
+        _ => {
+            let ms = kind.strip_prefix("delay:")?.parse().ok()?;
+            Fault::DelayCompletion(Duration::from_millis(ms))
+        }
+    };
+
+    if let Some(n) = when.strip_prefix("every:") {
+        match n.parse().ok()? {
+            0 => None,
+            n => Some(schedule.every(n, fault)),
+        }
+    } else if let Some(n) = when.strip_prefix("from:") {
+        Some(schedule.from(n.parse().ok()?, fault))
+    } else {
+        Some(schedule.at(when.parse().ok()?, fault))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn run(opts: &Options) -> std::io::Result<()> {
+    use std::fs;
+    use std::io;
+    use std::os::unix::fs::symlink;
+    use visca::{Simulator, VirtualSerialPort};
+
+    let mut port = VirtualSerialPort::open()?;
+
+    if let Some(link) = &opts.link {
+        if fs::symlink_metadata(link).is_ok() {
+            fs::remove_file(link)?;
+        }
+        symlink(port.path(), link)?;
+    }
+
+    println!("{}", port.path().display());
+
+    let mut sim = Simulator::new();
+    sim.set_address(opts.address)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+    sim.set_faults(opts.faults.clone());
+    port.serve(&mut sim)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run(_opts: &Options) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "pseudo-terminals are only supported on Linux",
+    ))
+}
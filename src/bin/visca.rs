@@ -0,0 +1,614 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::process;
+use std::thread;
+use std::time::Duration;
+use visca::{
+    discover, dissect, hex, open_capture, parse_hex, scan, Camera, Direction, Flow, Gateway,
+    PanTiltValue, Recorder, Request, Result, Script, ScriptRunner, DEFAULT_BAUD_RATE,
+    VISCA_IP_PORT, VISCA_TCP_PORT,
+};
+
+const USAGE: &str = "\
+Usage: visca [OPTIONS] <COMMAND>
+
+Options:
+    --port <PATH>         Serial port the camera is attached to [default: /dev/ttyUSB0]
+    --baud <RATE>         Serial baud rate [default: 9600]
+    --address <N>         Camera address on the serial bus, 1-7 [default: 1]
+    --udp <HOST[:PORT]>   Talk VISCA-over-IP instead of serial [default port: 52381]
+    --tcp <HOST[:PORT]>   Talk raw VISCA over TCP instead of serial [default port: 5678]
+    --record <FILE>       Write every packet exchanged with the camera to a capture file
+    --camera <NAME=N|HOST[:PORT]>
+                          Name a camera for scripts: camera N on the serial bus, or a
+                          VISCA-over-IP camera at HOST. Repeat for each camera; the first
+                          is the one statements outside camera blocks apply to
+    --json                Print results as JSON
+
+Commands:
+    pan-tilt get
+    pan-tilt set <PAN> <TILT>
+    pan-tilt drive <DIRECTION> [PAN_SPEED TILT_SPEED]
+    pan-tilt home
+    zoom get
+    zoom set <POSITION>
+    focus get
+    focus set <POSITION>
+    preset set|recall|reset <N>
+    power on|off
+    version
+    status                Read the lens, exposure and other settings in one go
+    raw <HEX>...
+    script <FILE>         Run a macro script of camera commands
+    capture <FILE>        Print a capture file, describing each packet
+    discover              List the VISCA-over-IP cameras on the local network
+    scan                  Look for cameras on every serial port, assigning their addresses
+    gateway [LISTEN=N]... Serve VISCA-over-IP on each LISTEN address, forwarding to camera N
+                          on the serial port [default: 0.0.0.0:52381=<--address>]
+
+Directions are up, down, left, right, up-left, up-right, down-left, down-right and stop.
+Numbers can be given in decimal or as hex with a 0x prefix.";
+
+struct Options {
+    port: String,
+    baud: u32,
+    address: u8,
+    udp: Option<String>,
+    tcp: Option<String>,
+    record: Option<String>,
+    cameras: Vec<(String, Target)>,
+    json: bool,
+}
+
+/// Where a camera named with `--camera` is reached.
+enum Target {
+    /// An address on the connection the other options set up.
+    Address(u8),
+    Host(String),
+}
+
+enum Action {
+    PanTiltGet,
+    PanTiltSet(PanTiltValue),
+    PanTiltDrive(Direction, u8, u8),
+    PanTiltHome,
+    ZoomGet,
+    ZoomSet(u16),
+    FocusGet,
+    FocusSet(u16),
+    PresetSet(u8),
+    PresetRecall(u8),
+    PresetReset(u8),
+    Power(bool),
+    Version,
+    Status,
+    Raw(Request),
+    Script(String),
+    Capture(String),
+    Discover,
+    Scan,
+    Gateway(Vec<(String, u8)>),
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let (opts, action) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(msg) => {
+            eprintln!("visca: {}\n\n{}", msg, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&opts, action) {
+        eprintln!("visca: {}", err);
+        process::exit(1);
+    }
+}
+
+fn parse_args(args: &[String]) -> std::result::Result<(Options, Action), String> {
+    let mut opts = Options {
+        port: "/dev/ttyUSB0".to_string(),
+        baud: DEFAULT_BAUD_RATE,
+        address: 1,
+        udp: None,
+        tcp: None,
+        record: None,
+        cameras: Vec::new(),
+        json: false,
+    };
+    let mut words = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+
+        match arg.as_str() {
+            "--port" => opts.port = value(arg)?,
+            "--baud" => opts.baud = parse_num(&value(arg)?)?,
+            "--address" => opts.address = parse_num(&value(arg)?)?,
+            "--udp" => opts.udp = Some(value(arg)?),
+            "--tcp" => opts.tcp = Some(value(arg)?),
+            "--record" => opts.record = Some(value(arg)?),
+            "--camera" => opts.cameras.push(parse_camera(&value(arg)?)?),
+            "--json" => opts.json = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => words.push(arg.as_str()),
+        }
+    }
+
+    if opts.udp.is_some() && opts.tcp.is_some() {
+        return Err("--udp and --tcp cannot be used together".to_string());
+    }
+
+    let action = match words.as_slice() {
+        ["pan-tilt", "get"] => Action::PanTiltGet,
+        ["pan-tilt", "set", pan, tilt] => Action::PanTiltSet(PanTiltValue {
+            pan: parse_signed(pan)?,
+            tilt: parse_signed(tilt)?,
+        }),
+        ["pan-tilt", "drive", dir] => Action::PanTiltDrive(parse_direction(dir)?, 0x01, 0x01),
+        ["pan-tilt", "drive", dir, pan_speed, tilt_speed] => Action::PanTiltDrive(
+            parse_direction(dir)?,
+            parse_num(pan_speed)?,
+            parse_num(tilt_speed)?,
+        ),
+        ["pan-tilt", "home"] => Action::PanTiltHome,
+        ["zoom", "get"] => Action::ZoomGet,
+        ["zoom", "set", pos] => Action::ZoomSet(parse_num(pos)?),
+        ["focus", "get"] => Action::FocusGet,
+        ["focus", "set", pos] => Action::FocusSet(parse_num(pos)?),
+        ["preset", "set", num] => Action::PresetSet(parse_num(num)?),
+        ["preset", "recall", num] => Action::PresetRecall(parse_num(num)?),
+        ["preset", "reset", num] => Action::PresetReset(parse_num(num)?),
+        ["power", "on"] => Action::Power(true),
+        ["power", "off"] => Action::Power(false),
+        ["version"] => Action::Version,
+        ["status"] => Action::Status,
+        ["raw", bytes @ ..] if !bytes.is_empty() => {
+            let bytes = parse_hex(&bytes.join(" ")).ok_or("invalid hex bytes")?;
+            let req = Request::from_bytes(&bytes).map_err(|err| err.to_string())?;
+            Action::Raw(req)
+        }
+        ["script", path] => Action::Script(path.to_string()),
+        ["capture", path] => Action::Capture(path.to_string()),
+        ["discover"] => Action::Discover,
+        ["scan"] => Action::Scan,
+        ["gateway"] => Action::Gateway(vec![(format!("0.0.0.0:{}", VISCA_IP_PORT), opts.address)]),
+        ["gateway", routes @ ..] => Action::Gateway(
+            routes
+                .iter()
+                .map(|route| parse_route(route))
+                .collect::<std::result::Result<_, _>>()?,
+        ),
+        [] => return Err("no command given".to_string()),
+        _ => return Err(format!("unknown command: {}", words.join(" "))),
+    };
+
+    if !opts.cameras.is_empty() && !matches!(action, Action::Script(_)) {
+        return Err("--camera only applies to scripts".to_string());
+    }
+
+    Ok((opts, action))
+}
+
+fn parse_num<T: std::convert::TryFrom<u32>>(s: &str) -> std::result::Result<T, String> {
+    let n = if let Some(digits) = s.strip_prefix("0x") {
+        u32::from_str_radix(digits, 16).ok()
+    } else {
+        s.parse().ok()
+    };
+
+    n.and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| format!("invalid number: {}", s))
+}
+
+fn parse_signed(s: &str) -> std::result::Result<i16, String> {
+    if s.starts_with("0x") {
+        parse_num::<u16>(s).map(|n| n as i16)
+    } else {
+        s.parse().map_err(|_| format!("invalid number: {}", s))
+    }
+}
+
+fn parse_direction(s: &str) -> std::result::Result<Direction, String> {
+    match s {
+        "up" => Ok(Direction::Up),
+        "down" => Ok(Direction::Down),
+        "left" => Ok(Direction::Left),
+        "right" => Ok(Direction::Right),
+        "up-left" => Ok(Direction::UpLeft),
+        "up-right" => Ok(Direction::UpRight),
+        "down-left" => Ok(Direction::DownLeft),
+        "down-right" => Ok(Direction::DownRight),
+        "stop" => Ok(Direction::Stop),
+        _ => Err(format!("unknown direction: {}", s)),
+    }
+}
+
+fn parse_route(s: &str) -> std::result::Result<(String, u8), String> {
+    let (listen, address) = s
+        .rsplit_once('=')
+        .ok_or_else(|| format!("invalid route: {}", s))?;
+    Ok((listen.to_string(), parse_num(address)?))
+}
+
+fn parse_camera(s: &str) -> std::result::Result<(String, Target), String> {
+    let (name, target) = s
+        .split_once('=')
+        .filter(|(name, target)| !name.is_empty() && !target.is_empty())
+        .ok_or_else(|| format!("invalid camera: {}", s))?;
+
+    let target = if target.bytes().all(|b| b.is_ascii_digit()) {
+        Target::Address(parse_num(target)?)
+    } else {
+        Target::Host(target.to_string())
+    };
+    Ok((name.to_string(), target))
+}
+
+fn open_camera(opts: &Options) -> Result<Camera> {
+    let mut camera = match (&opts.udp, &opts.tcp) {
+        (Some(host), None) if host.contains(':') => Camera::connect_udp(host.as_str())?,
+        (Some(host), None) => Camera::connect_udp((host.as_str(), VISCA_IP_PORT))?,
+        (None, Some(host)) if host.contains(':') => Camera::connect_tcp(host.as_str())?,
+        (None, Some(host)) => Camera::connect_tcp((host.as_str(), VISCA_TCP_PORT))?,
+        _ => Camera::open_with_baud_rate(&opts.port, opts.baud)?,
+    };
+
+    camera.set_address(opts.address)?;
+
+    if let Some(path) = &opts.record {
+        camera.set_recorder(Some(Recorder::create(path)?));
+    }
+
+    Ok(camera)
+}
+
+fn run(opts: &Options, action: Action) -> Result<()> {
+    let out = Output { json: opts.json };
+
+    if let Action::Capture(path) = &action {
+        return print_capture(path, &out);
+    }
+
+    if let Action::Discover = action {
+        for candidate in discover(Duration::from_secs(1))? {
+            out.fields(&[
+                ("name", candidate.name.into()),
+                ("model", candidate.model.into()),
+                ("ip", candidate.ip.to_string().into()),
+                ("mac", candidate.mac.into()),
+            ]);
+        }
+        return Ok(());
+    }
+
+    if let Action::Scan = action {
+        for found in scan()? {
+            for camera in found.cameras {
+                let model = camera
+                    .version
+                    .map(|version| format!("{:#06x}", version.model_id))
+                    .unwrap_or_default();
+
+                out.fields(&[
+                    ("port", found.port.as_str().into()),
+                    ("baud", found.baud_rate.into()),
+                    ("address", camera.address.into()),
+                    ("model_id", model.into()),
+                ]);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Action::Script(path) = &action {
+        let script = Script::parse(&fs::read_to_string(path)?)?;
+        script_runner(opts)?.run(&script)?;
+        out.ok();
+        return Ok(());
+    }
+
+    let mut camera = open_camera(opts)?;
+
+    if let Action::Gateway(routes) = action {
+        let mut gateway = Gateway::new();
+        let bus = gateway.add_bus(camera);
+        for (listen, address) in routes {
+            let addr = gateway.route(listen.as_str(), bus, address)?;
+            eprintln!("visca: forwarding {} to camera {}", addr, address);
+        }
+
+        let errors = gateway.errors();
+        thread::spawn(move || {
+            for err in errors {
+                eprintln!("visca: {}", err);
+            }
+        });
+        return gateway.run().map_err(Into::into);
+    }
+
+    match action {
+        Action::PanTiltGet => {
+            let val = camera.pan_tilt().get()?;
+            out.fields(&[("pan", val.pan.into()), ("tilt", val.tilt.into())]);
+        }
+        Action::PanTiltSet(val) => {
+            camera.pan_tilt().set_absolute(val)?;
+            out.ok();
+        }
+        Action::PanTiltDrive(dir, pan_speed, tilt_speed) => {
+            camera.pan_tilt().drive(dir, pan_speed, tilt_speed)?;
+            out.ok();
+        }
+        Action::PanTiltHome => {
+            camera.pan_tilt().home()?;
+            out.ok();
+        }
+        Action::ZoomGet => {
+            let pos = camera.zoom().get()?;
+            out.fields(&[("zoom", pos.into())]);
+        }
+        Action::ZoomSet(pos) => {
+            camera.zoom().set(pos)?;
+            out.ok();
+        }
+        Action::FocusGet => {
+            let pos = camera.focus().get()?;
+            out.fields(&[("focus", pos.into())]);
+        }
+        Action::FocusSet(pos) => {
+            camera.focus().set(pos)?;
+            out.ok();
+        }
+        Action::PresetSet(num) => {
+            camera.presets().set(num)?;
+            out.ok();
+        }
+        Action::PresetRecall(num) => {
+            camera.presets().recall(num)?;
+            out.ok();
+        }
+        Action::PresetReset(num) => {
+            camera.presets().reset(num)?;
+            out.ok();
+        }
+        Action::Power(on) => {
+            if on {
+                camera.power().on()?;
+            } else {
+                camera.power().off()?;
+            }
+            out.ok();
+        }
+        Action::Version => {
+            let version = camera.version()?;
+            out.fields(&[
+                ("vendor_id", version.vendor_id.into()),
+                ("model_id", version.model_id.into()),
+                ("rom_version", version.rom_version.into()),
+                ("max_socket", version.max_socket.into()),
+            ]);
+        }
+        Action::Status => {
+            let status = camera.status()?;
+            out.fields(&[
+                ("zoom", status.lens.zoom.into()),
+                ("focus", status.lens.focus.into()),
+                ("auto_focus", status.lens.auto_focus.into()),
+                (
+                    "white_balance",
+                    format!("{:?}", status.camera.white_balance).into(),
+                ),
+                ("exposure", format!("{:?}", status.camera.exposure).into()),
+                ("shutter", status.camera.shutter.into()),
+                ("iris", status.camera.iris.into()),
+                ("gain", status.camera.gain.into()),
+                ("power", status.other.power.into()),
+            ]);
+        }
+        Action::Raw(req) => {
+            let reply = camera.send_request(&req)?;
+            let desc = dissect(reply.as_bytes());
+
+            out.fields(&[
+                ("reply", hex(reply.as_bytes()).into()),
+                ("description", desc.to_string().into()),
+            ]);
+        }
+        Action::Script(_)
+        | Action::Capture(_)
+        | Action::Discover
+        | Action::Scan
+        | Action::Gateway(_) => {
+            unreachable!()
+        }
+    }
+
+    if let Some(err) = camera.recording_error() {
+        eprintln!("visca: stopped recording after a write error: {}", err);
+    }
+
+    Ok(())
+}
+
+/// Sets up the cameras named with `--camera`, or the one camera the other options point
+/// at if none are.
+fn script_runner(opts: &Options) -> Result<ScriptRunner> {
+    let mut runner = ScriptRunner::new();
+    if opts.cameras.is_empty() {
+        runner.add_camera("camera", open_camera(opts)?);
+        return Ok(runner);
+    }
+
+    // Cameras given by address share the one connection, which the first of them opens.
+    let mut bus: Option<&str> = None;
+    for (name, target) in &opts.cameras {
+        match (target, bus) {
+            (Target::Address(address), Some(bus)) => {
+                runner.add_camera_on_bus(name, bus, *address)?;
+            }
+            (Target::Address(address), None) => {
+                let mut camera = open_camera(opts)?;
+                camera.set_address(*address)?;
+                runner.add_camera(name, camera);
+                bus = Some(name);
+            }
+            (Target::Host(host), _) if host.contains(':') => {
+                runner.add_camera(name, Camera::connect_udp(host.as_str())?);
+            }
+            (Target::Host(host), _) => {
+                runner.add_camera(name, Camera::connect_udp((host.as_str(), VISCA_IP_PORT))?);
+            }
+        }
+    }
+
+    Ok(runner)
+}
+
+fn print_capture(path: &str, out: &Output) -> Result<()> {
+    for record in open_capture(path)? {
+        let timestamp = format!(
+            "{}.{:06}",
+            record.timestamp.as_secs(),
+            record.timestamp.subsec_micros()
+        );
+        let flow = match record.flow {
+            Flow::Sent => "sent",
+            Flow::Received => "received",
+        };
+
+        if out.json {
+            out.fields(&[
+                ("timestamp", timestamp.into()),
+                ("flow", flow.into()),
+                ("address", record.address.into()),
+                ("bytes", hex(&record.bytes).into()),
+                ("description", record.describe().to_string().into()),
+            ]);
+        } else {
+            let arrow = if record.flow == Flow::Sent { '>' } else { '<' };
+            println!(
+                "{} {} {:<26} {}",
+                timestamp,
+                arrow,
+                hex(&record.bytes),
+                record.describe()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+struct Output {
+    json: bool,
+}
+
+/// A field printed by `Output`, kept typed so that JSON output can tell numbers and
+/// booleans from strings.
+enum Value {
+    Number(i64),
+    Bool(bool),
+    Text(String),
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+macro_rules! number_value {
+    ($($ty:ty),*) => {
+        $(impl From<$ty> for Value {
+            fn from(n: $ty) -> Self {
+                Value::Number(n.into())
+            }
+        })*
+    };
+}
+
+number_value!(u8, u16, i16, u32);
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Text(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Text(s.to_string())
+    }
+}
+
+impl Value {
+    fn to_json(&self) -> String {
+        match self {
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Text(s) => json_string(s),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => n.fmt(f),
+            Value::Bool(b) => b.fmt(f),
+            Value::Text(s) => s.fmt(f),
+        }
+    }
+}
+
+impl Output {
+    fn ok(&self) {
+        if self.json {
+            println!("{{\"ok\": true}}");
+        }
+    }
+
+    /// Prints fields, either as a JSON object or as `name: value` lines.
+    fn fields(&self, fields: &[(&str, Value)]) {
+        if self.json {
+            let members: Vec<_> = fields
+                .iter()
+                .map(|(name, val)| format!("\"{}\": {}", name, val.to_json()))
+                .collect();
+            println!("{{{}}}", members.join(", "));
+        } else {
+            for (name, val) in fields {
+                println!("{}: {}", name, val);
+            }
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,150 @@
+use crate::commands::{PanTilt, PanTiltValue};
+use crate::models::Model;
+use crate::{Error, Result};
+
+/// Relates a model's raw pan-tilt positions to angles.
+///
+/// Angles are in degrees, positive to the right and up, measured from the direction the
+/// camera faces at its `center` position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Calibration {
+    pub pan_units_per_degree: f64,
+    pub tilt_units_per_degree: f64,
+    /// The raw position the camera faces straight ahead at.
+    pub center: PanTiltValue,
+    pub pan_min: f64,
+    pub pan_max: f64,
+    pub tilt_min: f64,
+    pub tilt_max: f64,
+}
+
+impl Calibration {
+    /// Returns the calibration of the model that reports `model_id`, if it's known.
+    pub fn for_model(model_id: u16) -> Option<Self> {
+        Model::for_id(model_id).map(|model| model.calibration)
+    }
+}
+
+/// A pan-tilt position in degrees.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Degrees {
+    pub pan: f64,
+    pub tilt: f64,
+}
+
+impl PanTiltValue {
+    /// Converts an angle to the nearest raw position, failing with `Error::OutOfRange` if
+    /// it lies outside the calibrated limits.
+    pub fn from_degrees(degrees: Degrees, cal: &Calibration) -> Result<Self> {
+        let in_range = (cal.pan_min..=cal.pan_max).contains(&degrees.pan)
+            && (cal.tilt_min..=cal.tilt_max).contains(&degrees.tilt);
+        if !in_range {
+            return Err(Error::OutOfRange);
+        }
+
+        let pan = cal.center.pan as f64 + degrees.pan * cal.pan_units_per_degree;
+        let tilt = cal.center.tilt as f64 + degrees.tilt * cal.tilt_units_per_degree;
+        if !(i16::MIN as f64..=i16::MAX as f64).contains(&pan)
+            || !(i16::MIN as f64..=i16::MAX as f64).contains(&tilt)
+        {
+            return Err(Error::OutOfRange);
+        }
+
+        Ok(PanTiltValue {
+            pan: pan.round() as i16,
+            tilt: tilt.round() as i16,
+        })
+    }
+
+    pub fn to_degrees(self, cal: &Calibration) -> Degrees {
+        Degrees {
+            pan: (self.pan as f64 - cal.center.pan as f64) / cal.pan_units_per_degree,
+            tilt: (self.tilt as f64 - cal.center.tilt as f64) / cal.tilt_units_per_degree,
+        }
+    }
+}
+
+impl<'a> PanTilt<'a> {
+    pub fn get_degrees(&mut self, cal: &Calibration) -> Result<Degrees> {
+        self.get().map(|val| val.to_degrees(cal))
+    }
+
+    pub fn set_absolute_degrees(&mut self, degrees: Degrees, cal: &Calibration) -> Result<()> {
+        let val = PanTiltValue::from_degrees(degrees, cal)?;
+        self.set_absolute(val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulator;
+    use crate::testing::camera_with;
+
+    const OFFSET: Calibration = Calibration {
+        pan_units_per_degree: 10.0,
+        tilt_units_per_degree: 20.0,
+        center: PanTiltValue {
+            pan: 100,
+            tilt: -50,
+        },
+        pan_min: -90.0,
+        pan_max: 90.0,
+        tilt_min: -20.0,
+        tilt_max: 60.0,
+    };
+
+    #[test]
+    fn test_from_degrees() {
+        let degrees = Degrees {
+            pan: -12.34,
+            tilt: 45.0,
+        };
+
+        assert_eq!(
+            PanTiltValue::from_degrees(degrees, &OFFSET).unwrap(),
+            PanTiltValue {
+                pan: -23,
+                tilt: 850
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_degrees() {
+        let val = PanTiltValue { pan: 100, tilt: 0 };
+        assert_eq!(
+            val.to_degrees(&OFFSET),
+            Degrees {
+                pan: 0.0,
+                tilt: 2.5
+            }
+        );
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        for &(pan, tilt) in &[(90.5, 0.0), (-91.0, 0.0), (0.0, -20.1), (0.0, 61.0)] {
+            match PanTiltValue::from_degrees(Degrees { pan, tilt }, &OFFSET) {
+                Err(Error::OutOfRange) => {}
+                other => panic!("unexpected result for {}, {}: {:?}", pan, tilt, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_model_degrees() {
+        let mut sim = Simulator::new();
+        sim.set_pan_tilt(PanTiltValue {
+            pan: 2267,
+            tilt: -400,
+        });
+        let mut camera = camera_with(sim);
+
+        let model_id = camera.version().unwrap().model_id;
+        let cal = Calibration::for_model(model_id).unwrap();
+        let degrees = camera.pan_tilt().get_degrees(&cal).unwrap();
+        assert!((degrees.pan - 170.0).abs() < 1e-9);
+        assert!((degrees.tilt + 30.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,266 @@
+//! Recording of the raw traffic between a controller and its cameras.
+//!
+//! A capture file is plain text with one packet per line:
+//!
+//! ```text
+//! # visca capture
+//! 1697040000.123456 > 1 81 01 06 01 18 14 03 01 FF
+//! 1697040000.140021 < 1 90 41 FF
+//! 1697040000.402310 < 1 90 51 FF
+//! ```
+//!
+//! The fields are separated by single spaces: the time the packet was sent or received, in
+//! seconds since the Unix epoch with microsecond precision; `>` for packets sent to the
+//! camera or `<` for packets received from it; the camera address; and finally the bytes
+//! of the packet as hex pairs. Blank lines and lines starting with `#` are ignored.
+
+use crate::dissect::{dissect, hex, parse_hex, Description};
+use crate::{Error, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HEADER: &str = "# visca capture";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Flow {
+    /// A request sent by the controller to a camera.
+    Sent,
+    /// A reply received by the controller from a camera.
+    Received,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Record {
+    /// The time since the Unix epoch at which the packet was sent or received.
+    pub timestamp: Duration,
+    pub flow: Flow,
+    pub address: u8,
+    pub bytes: Vec<u8>,
+}
+
+impl Record {
+    pub fn new(flow: Flow, bytes: &[u8]) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let address = match (flow, bytes.first()) {
+            (Flow::Sent, Some(b)) => b & 0x0f,
+            (Flow::Received, Some(b)) => (b >> 4) & 0x07,
+            (_, None) => 0,
+        };
+
+        Record {
+            timestamp,
+            flow,
+            address,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    pub fn describe(&self) -> Description {
+        dissect(&self.bytes)
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arrow = match self.flow {
+            Flow::Sent => '>',
+            Flow::Received => '<',
+        };
+
+        write!(
+            f,
+            "{}.{:06} {} {} {}",
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros(),
+            arrow,
+            self.address,
+            hex(&self.bytes)
+        )
+    }
+}
+
+impl FromStr for Record {
+    type Err = ();
+
+    fn from_str(line: &str) -> std::result::Result<Self, ()> {
+        let mut fields = line.splitn(4, ' ');
+        let mut next = || fields.next().ok_or(());
+
+        let (secs, micros) = next()?.split_once('.').ok_or(())?;
+        if micros.len() != 6 {
+            return Err(());
+        }
+        let timestamp = Duration::new(
+            secs.parse().map_err(|_| ())?,
+            micros.parse::<u32>().map_err(|_| ())? * 1000,
+        );
+
+        let flow = match next()? {
+            ">" => Flow::Sent,
+            "<" => Flow::Received,
+            _ => return Err(()),
+        };
+
+        let address = next()?.parse().map_err(|_| ())?;
+        let bytes = parse_hex(next()?).ok_or(())?;
+
+        Ok(Record {
+            timestamp,
+            flow,
+            address,
+            bytes,
+        })
+    }
+}
+
+/// Writes every packet passing through an `Interface` to a capture file.
+pub struct Recorder {
+    out: Box<dyn Write + Send>,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::create(path)?;
+        Recorder::new(LineWriter::new(file))
+    }
+
+    pub fn new<W: Write + Send + 'static>(out: W) -> Result<Self> {
+        let mut out = Box::new(out);
+        writeln!(out, "{}", HEADER)?;
+        Ok(Recorder { out })
+    }
+
+    pub fn record(&mut self, flow: Flow, bytes: &[u8]) -> Result<()> {
+        writeln!(self.out, "{}", Record::new(flow, bytes)).map_err(Error::Io)
+    }
+}
+
+pub fn read_capture<R: BufRead>(reader: R) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let record = line.parse().map_err(|_| Error::InvalidCapture(i + 1))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+pub fn open_capture<P: AsRef<Path>>(path: P) -> Result<Vec<Record>> {
+    let file = File::open(path)?;
+    read_capture(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::camera;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A capture file on a disk that can be made to fill up.
+    #[derive(Clone, Default)]
+    struct Disk(Arc<AtomicBool>);
+
+    impl Write for Disk {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.0.load(Ordering::SeqCst) {
+                Err(std::io::Error::other("no space left on device"))
+            } else {
+                Ok(buf.len())
+            }
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_error_stops_recording() {
+        let disk = Disk::default();
+        let mut camera = camera();
+        camera.set_recorder(Some(Recorder::new(disk.clone()).unwrap()));
+        disk.0.store(true, Ordering::SeqCst);
+
+        camera.zoom().set(0x1000).unwrap();
+        assert!(!camera.is_recording());
+        assert!(camera.recording_error().is_some());
+        assert!(camera.power().get().unwrap());
+    }
+
+    #[test]
+    fn test_record_format() {
+        let record = Record {
+            timestamp: Duration::new(1697040000, 123_456_000),
+            flow: Flow::Sent,
+            address: 1,
+            bytes: vec![0x81, 0x09, 0x06, 0x12, 0xff],
+        };
+
+        let line = record.to_string();
+        assert_eq!(line, "1697040000.123456 > 1 81 09 06 12 FF");
+        assert_eq!(line.parse(), Ok(record));
+    }
+
+    #[test]
+    fn test_recorder_roundtrip() {
+        let buf = SharedBuf::default();
+        let mut recorder = Recorder::new(buf.clone()).unwrap();
+        recorder
+            .record(Flow::Sent, &[0x82, 0x09, 0x04, 0x47, 0xff])
+            .unwrap();
+        recorder
+            .record(Flow::Received, &[0xa0, 0x50, 0x00, 0x01, 0x02, 0x03, 0xff])
+            .unwrap();
+
+        let bytes = buf.0.lock().unwrap().clone();
+        let records = read_capture(&bytes[..]).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].flow, Flow::Sent);
+        assert_eq!(records[0].address, 2);
+        assert_eq!(records[1].flow, Flow::Received);
+        assert_eq!(records[1].address, 2);
+        assert_eq!(
+            records[1].bytes,
+            &[0xa0, 0x50, 0x00, 0x01, 0x02, 0x03, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_capture() {
+        let capture = "# visca capture\n1.000000 > 1 81 09 06 12 FF\n1.5 ? 1 90 41 FF\n";
+
+        match read_capture(capture.as_bytes()) {
+            Err(Error::InvalidCapture(line)) => assert_eq!(line, 3),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
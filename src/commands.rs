@@ -1,182 +1,153 @@
 use crate::interface::Interface;
-use crate::packet::{Message, Reply, Request};
+use crate::protocol::{Command, Direction, Inquiry, InquiryReply, Version};
+use crate::status::CameraStatus;
+use crate::wait::{settle, wait_until, within, Settled};
 use crate::{Error, Result};
+use std::time::Duration;
+
+pub(crate) fn execute(iface: &mut Interface, address: u8, cmd: Command) -> Result<()> {
+    let req = cmd.encode(address)?;
+    iface.send_request_with_reply(&req).and_then(|reply| {
+        if reply.payload().is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidReply)
+        }
+    })
+}
 
-fn check_empty_reply(reply: Reply) -> Result<()> {
-    match reply.message() {
-        Message::Completion(&[]) => Ok(()),
-        _ => Err(Error::InvalidReply),
-    }
+pub(crate) fn inquire(iface: &mut Interface, address: u8, inq: Inquiry) -> Result<InquiryReply> {
+    let req = inq.encode(address)?;
+    iface
+        .send_request_with_reply(&req)
+        .and_then(|reply| inq.decode_reply(reply.payload()))
 }
 
 pub struct PanTilt<'a> {
     iface: &'a mut Interface,
+    address: u8,
 }
 
 impl<'a> PanTilt<'a> {
-    pub fn new(iface: &'a mut Interface) -> Self {
-        PanTilt { iface }
+    pub fn new(iface: &'a mut Interface, address: u8) -> Self {
+        PanTilt { iface, address }
     }
 
     pub fn get(&mut self) -> Result<PanTiltValue> {
-        let req = Request::new()
-            .address(1)
-            .inquiry()
-            .pan_tilter()
-            .payload(&[0x12]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(|reply| match reply.message() {
-                Message::Completion(payload) if payload.len() == PAN_TILT_VALUE_LEN => {
-                    Ok(PanTiltValue::from_bytes(payload))
-                }
-                _ => Err(Error::InvalidReply),
-            })
+        match inquire(self.iface, self.address, Inquiry::PanTiltPosition)? {
+            InquiryReply::PanTiltPosition(val) => Ok(val),
+            _ => Err(Error::InvalidReply),
+        }
     }
 
     pub fn set_absolute(&mut self, val: PanTiltValue) -> Result<()> {
-        let mut payload = [0; 11];
-        payload[..2].copy_from_slice(&[0x02, 0x01]);
-        payload[3..].copy_from_slice(&val.to_bytes());
-
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&payload);
+        let cmd = Command::PanTiltAbsolute {
+            pan_speed: 0x01,
+            tilt_speed: 0x01,
+            position: val,
+        };
+        execute(self.iface, self.address, cmd)
+    }
 
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+    /// Moves to `val` at the given speeds, which range as for `drive`.
+    pub fn set_absolute_at_speed(
+        &mut self,
+        val: PanTiltValue,
+        pan_speed: u8,
+        tilt_speed: u8,
+    ) -> Result<()> {
+        let cmd = Command::PanTiltAbsolute {
+            pan_speed,
+            tilt_speed,
+            position: val,
+        };
+        execute(self.iface, self.address, cmd)
     }
 
     pub fn set_relative(&mut self, val: PanTiltValue) -> Result<()> {
-        let mut payload = [0; 11];
-        payload[..2].copy_from_slice(&[0x03, 0x01]);
-        payload[3..].copy_from_slice(&val.to_bytes());
-
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&payload);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        let cmd = Command::PanTiltRelative {
+            pan_speed: 0x01,
+            tilt_speed: 0x01,
+            position: val,
+        };
+        execute(self.iface, self.address, cmd)
     }
 
     pub fn up(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x03, 0x01]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::Up, 0x01, 0x01)
     }
 
     pub fn down(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x03, 0x02]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::Down, 0x01, 0x01)
     }
 
     pub fn left(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x01, 0x03]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::Left, 0x01, 0x01)
     }
 
     pub fn right(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x02, 0x03]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::Right, 0x01, 0x01)
     }
 
     pub fn up_left(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x01, 0x01]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::UpLeft, 0x01, 0x01)
     }
 
     pub fn up_right(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x02, 0x01]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::UpRight, 0x01, 0x01)
     }
 
     pub fn down_left(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x01, 0x02]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::DownLeft, 0x01, 0x01)
     }
 
     pub fn down_right(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x02, 0x02]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        self.drive(Direction::DownRight, 0x01, 0x01)
     }
 
     pub fn stop(&mut self) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .pan_tilter()
-            .payload(&[0x01, 0x01, 0x01, 0x03, 0x03]);
+        self.drive(Direction::Stop, 0x01, 0x01)
+    }
+
+    pub fn home(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::PanTiltHome)
+    }
+
+    /// Polls the position until both axes are within `tolerance` of `target`, or until
+    /// the camera stops moving short of it, which `Settled::reached` tells apart.
+    pub fn wait_for_position(
+        &mut self,
+        target: PanTiltValue,
+        tolerance: u16,
+        timeout: Duration,
+    ) -> Result<Settled<PanTiltValue>> {
+        settle(
+            || self.get(),
+            |pos| {
+                within(pos.pan, target.pan, tolerance) && within(pos.tilt, target.tilt, tolerance)
+            },
+            timeout,
+        )
+    }
+
+    /// Polls the position until the camera stops moving.
+    pub fn wait_until_stopped(&mut self, timeout: Duration) -> Result<Settled<PanTiltValue>> {
+        settle(|| self.get(), |_| false, timeout)
+    }
 
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+    /// Starts moving in `direction` until `stop` is called. Speeds range from 0x01 up to a
+    /// model-dependent maximum, typically 0x18 for pan and 0x14 for tilt.
+    pub fn drive(&mut self, direction: Direction, pan_speed: u8, tilt_speed: u8) -> Result<()> {
+        let cmd = Command::PanTiltDrive {
+            direction,
+            pan_speed,
+            tilt_speed,
+        };
+        execute(self.iface, self.address, cmd)
     }
 }
 
-const PAN_TILT_VALUE_LEN: usize = 8;
+pub(crate) const PAN_TILT_VALUE_LEN: usize = 8;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct PanTiltValue {
@@ -185,7 +156,7 @@ pub struct PanTiltValue {
 }
 
 impl PanTiltValue {
-    fn from_bytes(bytes: &[u8]) -> Self {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
         assert_eq!(bytes.len(), PAN_TILT_VALUE_LEN);
 
         let mut pan = (bytes[0] as i16) << 12;
@@ -201,7 +172,7 @@ impl PanTiltValue {
         PanTiltValue { pan, tilt }
     }
 
-    fn to_bytes(&self) -> [u8; 8] {
+    pub(crate) fn to_bytes(self) -> [u8; 8] {
         let pan = self.pan as u16;
         let tilt = self.tilt as u16;
 
@@ -220,98 +191,634 @@ impl PanTiltValue {
 
 pub struct Presets<'a> {
     iface: &'a mut Interface,
+    address: u8,
 }
 
 impl<'a> Presets<'a> {
-    pub fn new(iface: &'a mut Interface) -> Self {
-        Presets { iface }
+    pub fn new(iface: &'a mut Interface, address: u8) -> Self {
+        Presets { iface, address }
     }
 
     pub fn reset(&mut self, num: u8) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .camera_1()
-            .payload(&[0x3f, 0x00, num & 0x0f]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        execute(self.iface, self.address, Command::PresetReset(num))
     }
 
     pub fn set(&mut self, num: u8) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .camera_1()
-            .payload(&[0x3f, 0x01, num & 0x0f]);
+        execute(self.iface, self.address, Command::PresetSet(num))
+    }
 
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+    /// Sets the pan-tilt speed of later recalls, from 0x01 up to a model-dependent
+    /// maximum, typically 0x18. Not every model supports this.
+    pub fn set_recall_speed(&mut self, speed: u8) -> Result<()> {
+        execute(self.iface, self.address, Command::PresetSpeed(speed))
     }
 
     pub fn recall(&mut self, num: u8) -> Result<()> {
-        let req = Request::new()
-            .address(1)
-            .command()
-            .camera_1()
-            .payload(&[0x3f, 0x02, num & 0x0f]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+        execute(self.iface, self.address, Command::PresetRecall(num))
     }
 }
 
 pub struct Zoom<'a> {
     iface: &'a mut Interface,
+    address: u8,
 }
 
 impl<'a> Zoom<'a> {
-    pub fn new(iface: &'a mut Interface) -> Self {
-        Zoom { iface }
+    pub fn new(iface: &'a mut Interface, address: u8) -> Self {
+        Zoom { iface, address }
+    }
+
+    pub fn get(&mut self) -> Result<u16> {
+        match inquire(self.iface, self.address, Inquiry::ZoomPosition)? {
+            InquiryReply::ZoomPosition(val) => Ok(val),
+            _ => Err(Error::InvalidReply),
+        }
+    }
+
+    pub fn set(&mut self, val: u16) -> Result<()> {
+        execute(self.iface, self.address, Command::ZoomDirect(val))
+    }
+
+    /// Starts zooming in until `stop` is called. Speeds range from 0 to 7.
+    pub fn tele(&mut self, speed: u8) -> Result<()> {
+        execute(self.iface, self.address, Command::ZoomTele(speed))
+    }
+
+    /// Starts zooming out until `stop` is called. Speeds range from 0 to 7.
+    pub fn wide(&mut self, speed: u8) -> Result<()> {
+        execute(self.iface, self.address, Command::ZoomWide(speed))
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::ZoomStop)
+    }
+
+    /// Zooms to `val` at a variable speed from 0 to 7, returning once it gets there.
+    ///
+    /// Zoom Direct has no speed, so the lens is driven towards `val` and then put at the
+    /// exact position once it has reached or passed it, which corrects any overshoot. A
+    /// lens that doesn't start moving within `timeout`, or that stops short of `val`, fails
+    /// with `Error::Timeout`.
+    pub fn set_at_speed(&mut self, val: u16, speed: u8, timeout: Duration) -> Result<()> {
+        let start = self.get()?;
+        if start == val {
+            return Ok(());
+        }
+
+        let tele = val > start;
+        if tele {
+            self.tele(speed)?;
+        } else {
+            self.wide(speed)?;
+        }
+
+        // Once the lens is moving, it stopping short, at the end of its range say, also
+        // ends the drive.
+        let reached = |pos: u16| if tele { pos >= val } else { pos <= val };
+        let result = wait_until(|| self.get(), |&pos| pos != start, timeout)
+            .and_then(|_| settle(|| self.get(), reached, timeout));
+        self.stop()?;
+        result?;
+
+        self.set(val)?;
+        let settled = self.wait_for_position(val, 0, timeout)?;
+        if !settled.reached {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+
+    /// Polls the position until it is within `tolerance` of `target`, or until the lens
+    /// stops moving short of it, which `Settled::reached` tells apart.
+    pub fn wait_for_position(
+        &mut self,
+        target: u16,
+        tolerance: u16,
+        timeout: Duration,
+    ) -> Result<Settled<u16>> {
+        settle(|| self.get(), |pos| within(pos, target, tolerance), timeout)
+    }
+
+    /// Polls the position until the lens stops moving.
+    pub fn wait_until_stopped(&mut self, timeout: Duration) -> Result<Settled<u16>> {
+        settle(|| self.get(), |_| false, timeout)
+    }
+}
+
+pub struct Focus<'a> {
+    iface: &'a mut Interface,
+    address: u8,
+}
+
+impl<'a> Focus<'a> {
+    pub fn new(iface: &'a mut Interface, address: u8) -> Self {
+        Focus { iface, address }
     }
 
     pub fn get(&mut self) -> Result<u16> {
-        let req = Request::new()
-            .address(1)
-            .inquiry()
-            .camera_1()
-            .payload(&[0x47]);
-
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(|reply| match reply.message() {
-                Message::Completion(payload) if payload.len() == 4 => {
-                    let mut val = (payload[0] as u16) << 12;
-                    val |= (payload[1] as u16) << 8;
-                    val |= (payload[2] as u16) << 4;
-                    val |= payload[3] as u16;
-
-                    Ok(val)
-                }
-                _ => Err(Error::InvalidReply),
-            })
+        match inquire(self.iface, self.address, Inquiry::FocusPosition)? {
+            InquiryReply::FocusPosition(val) => Ok(val),
+            _ => Err(Error::InvalidReply),
+        }
     }
 
     pub fn set(&mut self, val: u16) -> Result<()> {
-        let payload = &[
-            0x47,
-            ((val & 0xf000) >> 12) as u8,
-            ((val & 0x0f00) >> 8) as u8,
-            ((val & 0x00f0) >> 4) as u8,
-            (val & 0x000f) as u8,
+        execute(self.iface, self.address, Command::FocusDirect(val))
+    }
+
+    /// Starts focusing further away until `stop` is called. Speeds range from 0 to 7.
+    pub fn far(&mut self, speed: u8) -> Result<()> {
+        execute(self.iface, self.address, Command::FocusFar(speed))
+    }
+
+    /// Starts focusing closer until `stop` is called. Speeds range from 0 to 7.
+    pub fn near(&mut self, speed: u8) -> Result<()> {
+        execute(self.iface, self.address, Command::FocusNear(speed))
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::FocusStop)
+    }
+
+    pub fn auto(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::FocusAuto(true))
+    }
+
+    pub fn manual(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::FocusAuto(false))
+    }
+
+    /// Focuses once on the middle of the picture, while in manual focus.
+    pub fn one_push(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::FocusOnePush)
+    }
+
+    /// Polls the position until it is within `tolerance` of `target`, or until the lens
+    /// stops moving short of it, which `Settled::reached` tells apart.
+    pub fn wait_for_position(
+        &mut self,
+        target: u16,
+        tolerance: u16,
+        timeout: Duration,
+    ) -> Result<Settled<u16>> {
+        settle(|| self.get(), |pos| within(pos, target, tolerance), timeout)
+    }
+
+    /// Polls the position until the lens stops moving.
+    pub fn wait_until_stopped(&mut self, timeout: Duration) -> Result<Settled<u16>> {
+        settle(|| self.get(), |_| false, timeout)
+    }
+}
+
+pub struct Power<'a> {
+    iface: &'a mut Interface,
+    address: u8,
+}
+
+impl<'a> Power<'a> {
+    pub fn new(iface: &'a mut Interface, address: u8) -> Self {
+        Power { iface, address }
+    }
+
+    pub fn get(&mut self) -> Result<bool> {
+        match inquire(self.iface, self.address, Inquiry::Power)? {
+            InquiryReply::Power(on) => Ok(on),
+            _ => Err(Error::InvalidReply),
+        }
+    }
+
+    pub fn on(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::Power(true))
+    }
+
+    pub fn off(&mut self) -> Result<()> {
+        execute(self.iface, self.address, Command::Power(false))
+    }
+}
+
+pub(crate) fn version(iface: &mut Interface, address: u8) -> Result<Version> {
+    match inquire(iface, address, Inquiry::Version)? {
+        InquiryReply::Version(version) => Ok(version),
+        _ => Err(Error::InvalidReply),
+    }
+}
+
+pub(crate) fn status(iface: &mut Interface, address: u8) -> Result<CameraStatus> {
+    let lens = match inquire(iface, address, Inquiry::LensBlock)? {
+        InquiryReply::LensBlock(status) => status,
+        _ => return Err(Error::InvalidReply),
+    };
+    let camera = match inquire(iface, address, Inquiry::CameraBlock)? {
+        InquiryReply::CameraBlock(status) => status,
+        _ => return Err(Error::InvalidReply),
+    };
+    let other = match inquire(iface, address, Inquiry::OtherBlock)? {
+        InquiryReply::OtherBlock(status) => status,
+        _ => return Err(Error::InvalidReply),
+    };
+    let enlargement = match inquire(iface, address, Inquiry::EnlargementBlock)? {
+        InquiryReply::EnlargementBlock(status) => status,
+        _ => return Err(Error::InvalidReply),
+    };
+
+    Ok(CameraStatus {
+        lens,
+        camera,
+        other,
+        enlargement,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::packet::ErrorKind;
+    use crate::sim::Simulator;
+    use crate::status::ExposureMode;
+    use crate::testing::{sim_camera, sim_camera_with};
+
+    const ACK: &[u8] = &[0x90, 0x41, 0xff];
+    const COMPLETION: &[u8] = &[0x90, 0x51, 0xff];
+
+    fn with_mock<T>(mock: MockTransport, f: impl FnOnce(&mut Interface) -> T) -> T {
+        let mut iface = Interface::new(Box::new(mock));
+        f(&mut iface)
+    }
+
+    fn assert_command(request: &[u8], f: impl FnOnce(&mut Interface) -> Result<()>) {
+        let mut mock = MockTransport::new();
+        mock.expect(request).reply(ACK).reply(COMPLETION);
+        with_mock(mock, f).unwrap();
+    }
+
+    fn assert_inquiry<T>(
+        request: &[u8],
+        reply: &[u8],
+        f: impl FnOnce(&mut Interface) -> Result<T>,
+    ) -> T {
+        let mut mock = MockTransport::new();
+        mock.expect(request).reply(reply);
+        with_mock(mock, f).unwrap()
+    }
+
+    #[test]
+    fn test_pan_tilt_get() {
+        let val = assert_inquiry(
+            &[0x81, 0x09, 0x06, 0x12, 0xff],
+            &[
+                0x90, 0x50, 0x0f, 0x0f, 0x0b, 0x00, 0x00, 0x00, 0x03, 0x02, 0xff,
+            ],
+            |iface| PanTilt::new(iface, 1).get(),
+        );
+        assert_eq!(val, PanTiltValue { pan: -80, tilt: 50 });
+    }
+
+    #[test]
+    fn test_pan_tilt_set_absolute() {
+        assert_command(
+            &[
+                0x81, 0x01, 0x06, 0x02, 0x01, 0x01, 0x0f, 0x0f, 0x0b, 0x00, 0x00, 0x00, 0x03, 0x02,
+                0xff,
+            ],
+            |iface| PanTilt::new(iface, 1).set_absolute(PanTiltValue { pan: -80, tilt: 50 }),
+        );
+    }
+
+    #[test]
+    fn test_pan_tilt_set_absolute_at_speed() {
+        assert_command(
+            &[
+                0x81, 0x01, 0x06, 0x02, 0x18, 0x14, 0x0f, 0x0f, 0x0b, 0x00, 0x00, 0x00, 0x03, 0x02,
+                0xff,
+            ],
+            |iface| {
+                PanTilt::new(iface, 1).set_absolute_at_speed(
+                    PanTiltValue { pan: -80, tilt: 50 },
+                    0x18,
+                    0x14,
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn test_pan_tilt_set_relative() {
+        assert_command(
+            &[
+                0x81, 0x01, 0x06, 0x03, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x0f, 0x0f, 0x0f, 0x0e,
+                0xff,
+            ],
+            |iface| PanTilt::new(iface, 1).set_relative(PanTiltValue { pan: 16, tilt: -2 }),
+        );
+    }
+
+    #[test]
+    fn test_pan_tilt_directions() {
+        type Move = fn(&mut PanTilt) -> Result<()>;
+
+        let cases: &[(&[u8], Move)] = &[
+            (&[0x03, 0x01], |pt| pt.up()),
+            (&[0x03, 0x02], |pt| pt.down()),
+            (&[0x01, 0x03], |pt| pt.left()),
+            (&[0x02, 0x03], |pt| pt.right()),
+            (&[0x01, 0x01], |pt| pt.up_left()),
+            (&[0x02, 0x01], |pt| pt.up_right()),
+            (&[0x01, 0x02], |pt| pt.down_left()),
+            (&[0x02, 0x02], |pt| pt.down_right()),
+            (&[0x03, 0x03], |pt| pt.stop()),
         ];
 
-        let req = Request::new()
-            .address(1)
-            .command()
-            .camera_1()
-            .payload(payload);
+        for (dir, f) in cases {
+            let request = [0x81, 0x01, 0x06, 0x01, 0x01, 0x01, dir[0], dir[1], 0xff];
+            assert_command(&request, |iface| f(&mut PanTilt::new(iface, 1)));
+        }
+    }
+
+    #[test]
+    fn test_pan_tilt_drive() {
+        assert_command(
+            &[0x81, 0x01, 0x06, 0x01, 0x18, 0x14, 0x02, 0x01, 0xff],
+            |iface| PanTilt::new(iface, 1).drive(Direction::UpRight, 0x18, 0x14),
+        );
+    }
+
+    #[test]
+    fn test_pan_tilt_home() {
+        assert_command(&[0x81, 0x01, 0x06, 0x04, 0xff], |iface| {
+            PanTilt::new(iface, 1).home()
+        });
+    }
+
+    #[test]
+    fn test_zoom_get() {
+        let val = assert_inquiry(
+            &[0x81, 0x09, 0x04, 0x47, 0xff],
+            &[0x90, 0x50, 0x04, 0x00, 0x00, 0x00, 0xff],
+            |iface| Zoom::new(iface, 1).get(),
+        );
+        assert_eq!(val, 0x4000);
+    }
+
+    #[test]
+    fn test_zoom_set() {
+        assert_command(
+            &[0x81, 0x01, 0x04, 0x47, 0x01, 0x0b, 0x0c, 0x00, 0xff],
+            |iface| Zoom::new(iface, 1).set(0x1bc0),
+        );
+    }
+
+    #[test]
+    fn test_zoom_drive() {
+        assert_command(&[0x81, 0x01, 0x04, 0x07, 0x25, 0xff], |iface| {
+            Zoom::new(iface, 1).tele(5)
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x07, 0x30, 0xff], |iface| {
+            Zoom::new(iface, 1).wide(0)
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x07, 0x00, 0xff], |iface| {
+            Zoom::new(iface, 1).stop()
+        });
+    }
+
+    #[test]
+    fn test_focus_get() {
+        let val = assert_inquiry(
+            &[0x81, 0x09, 0x04, 0x48, 0xff],
+            &[0x90, 0x50, 0x0c, 0x00, 0x00, 0x00, 0xff],
+            |iface| Focus::new(iface, 1).get(),
+        );
+        assert_eq!(val, 0xc000);
+    }
+
+    #[test]
+    fn test_focus_set() {
+        assert_command(
+            &[0x81, 0x01, 0x04, 0x48, 0x01, 0x02, 0x03, 0x04, 0xff],
+            |iface| Focus::new(iface, 1).set(0x1234),
+        );
+    }
+
+    #[test]
+    fn test_focus_drive() {
+        assert_command(&[0x81, 0x01, 0x04, 0x08, 0x27, 0xff], |iface| {
+            Focus::new(iface, 1).far(7)
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x08, 0x32, 0xff], |iface| {
+            Focus::new(iface, 1).near(2)
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x08, 0x00, 0xff], |iface| {
+            Focus::new(iface, 1).stop()
+        });
+    }
+
+    #[test]
+    fn test_focus_modes() {
+        assert_command(&[0x81, 0x01, 0x04, 0x38, 0x02, 0xff], |iface| {
+            Focus::new(iface, 1).auto()
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x38, 0x03, 0xff], |iface| {
+            Focus::new(iface, 1).manual()
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x18, 0x01, 0xff], |iface| {
+            Focus::new(iface, 1).one_push()
+        });
+    }
+
+    #[test]
+    fn test_presets() {
+        assert_command(&[0x81, 0x01, 0x04, 0x3f, 0x00, 0x05, 0xff], |iface| {
+            Presets::new(iface, 1).reset(5)
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x3f, 0x01, 0x05, 0xff], |iface| {
+            Presets::new(iface, 1).set(5)
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x3f, 0x02, 0x05, 0xff], |iface| {
+            Presets::new(iface, 1).recall(5)
+        });
+        assert_command(&[0x81, 0x01, 0x7e, 0x01, 0x0b, 0x10, 0xff], |iface| {
+            Presets::new(iface, 1).set_recall_speed(0x10)
+        });
+    }
+
+    #[test]
+    fn test_power() {
+        assert_command(&[0x81, 0x01, 0x04, 0x00, 0x02, 0xff], |iface| {
+            Power::new(iface, 1).on()
+        });
+        assert_command(&[0x81, 0x01, 0x04, 0x00, 0x03, 0xff], |iface| {
+            Power::new(iface, 1).off()
+        });
+
+        let on = assert_inquiry(
+            &[0x81, 0x09, 0x04, 0x00, 0xff],
+            &[0x90, 0x50, 0x03, 0xff],
+            |iface| Power::new(iface, 1).get(),
+        );
+        assert!(!on);
+    }
+
+    #[test]
+    fn test_version() {
+        let val = assert_inquiry(
+            &[0x81, 0x09, 0x00, 0x02, 0xff],
+            &[0x90, 0x50, 0x00, 0x20, 0x05, 0x0a, 0x01, 0x00, 0x02, 0xff],
+            |iface| version(iface, 1),
+        );
+
+        assert_eq!(
+            val,
+            Version {
+                vendor_id: 0x0020,
+                model_id: 0x050a,
+                rom_version: 0x0100,
+                max_socket: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_status() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x09, 0x7e, 0x7e, 0x00, 0xff]).reply(&[
+            0x90, 0x50, 0x01, 0x02, 0x03, 0x04, 0x01, 0x00, 0x0a, 0x0b, 0x0c, 0x0d, 0x00, 0x01,
+            0x00, 0xff,
+        ]);
+        mock.expect(&[0x81, 0x09, 0x7e, 0x7e, 0x01, 0xff]).reply(&[
+            0x90, 0x50, 0x0e, 0x01, 0x0c, 0x00, 0x00, 0x05, 0x03, 0x00, 0x11, 0x0d, 0x02, 0x0f,
+            0x07, 0xff,
+        ]);
+        mock.expect(&[0x81, 0x09, 0x7e, 0x7e, 0x02, 0xff]).reply(&[
+            0x90, 0x50, 0x01, 0x05, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0xff,
+        ]);
+        mock.expect(&[0x81, 0x09, 0x7e, 0x7e, 0x03, 0xff]).reply(&[
+            0x90, 0x50, 0x00, 0x00, 0x04, 0x00, 0x00, 0x05, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0xff,
+        ]);
+
+        let status = with_mock(mock, |iface| status(iface, 1)).unwrap();
+
+        assert_eq!(status.lens.zoom, 0x1234);
+        assert_eq!(status.lens.focus, 0xabcd);
+        assert!(status.lens.auto_focus);
+        assert_eq!(status.camera.red_gain, 0xe1);
+        assert_eq!(status.camera.exposure, ExposureMode::Manual);
+        assert_eq!(status.camera.iris, 0x0d);
+        assert!(status.other.power);
+        assert!(status.other.mirror && status.other.flip && !status.other.freeze);
+        assert_eq!(status.other.picture_effect, 0x02);
+        assert_eq!(status.enlargement.digital_zoom, 0x0040);
+        assert_eq!(status.enlargement.af_activation_time, 0x05);
+        assert_eq!(status.enlargement.af_interval_time, 0x0a);
+    }
+
+    #[test]
+    fn test_status_short_block() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x09, 0x7e, 0x7e, 0x00, 0xff])
+            .reply(&[0x90, 0x50, 0x01, 0x02, 0x03, 0x04, 0xff]);
+
+        match with_mock(mock, |iface| status(iface, 1)) {
+            Err(Error::InvalidReply) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_camera_address() {
+        assert_command(&[0x83, 0x01, 0x04, 0x3f, 0x02, 0x01, 0xff], |iface| {
+            Presets::new(iface, 3).recall(1)
+        });
+    }
+
+    #[test]
+    fn test_completion_without_ack() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x01, 0x06, 0x04, 0xff])
+            .reply(COMPLETION);
+
+        with_mock(mock, |iface| PanTilt::new(iface, 1).home()).unwrap();
+    }
+
+    #[test]
+    fn test_error_reply() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x01, 0x04, 0x3f, 0x02, 0x05, 0xff])
+            .reply(ACK)
+            .reply(&[0x90, 0x61, 0x41, 0xff]);
+
+        match with_mock(mock, |iface| Presets::new(iface, 1).recall(5)) {
+            Err(Error::Camera(kind)) => assert_eq!(kind, ErrorKind::NotExecutable),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_reply_without_ack() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x09, 0x04, 0x47, 0xff])
+            .reply(&[0x90, 0x60, 0x03, 0xff]);
+
+        match with_mock(mock, |iface| Zoom::new(iface, 1).get()) {
+            Err(Error::Camera(kind)) => assert_eq!(kind, ErrorKind::FullBuffer),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_inquiry_reply() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x09, 0x04, 0x47, 0xff])
+            .reply(&[0x90, 0x50, 0x01, 0x02, 0xff]);
+
+        match with_mock(mock, |iface| Zoom::new(iface, 1).get()) {
+            Err(Error::InvalidReply) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_command_reply_with_payload() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x01, 0x04, 0x47, 0x00, 0x00, 0x00, 0x00, 0xff])
+            .reply(ACK)
+            .reply(&[0x90, 0x51, 0x01, 0xff]);
+
+        match with_mock(mock, |iface| Zoom::new(iface, 1).set(0)) {
+            Err(Error::InvalidReply) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_zoom_at_speed() {
+        let (mut camera, sim) = sim_camera();
+
+        camera
+            .zoom()
+            .set_at_speed(0x0800, 3, Duration::from_secs(2))
+            .unwrap();
+
+        let sim = sim.lock().unwrap();
+        assert_eq!(sim.zoom(), 0x0800);
+        assert!(!sim.is_moving());
+    }
 
-        self.iface
-            .send_request_with_reply(&req)
-            .and_then(check_empty_reply)
+    #[test]
+    fn test_zoom_at_speed_never_moves() {
+        // The lens is already at its tele end, so driving it further does nothing.
+        let mut sim = Simulator::new();
+        sim.set_zoom(0x4000);
+        let (mut camera, sim) = sim_camera_with(sim);
+
+        match camera
+            .zoom()
+            .set_at_speed(0x5000, 3, Duration::from_millis(500))
+        {
+            Err(Error::Timeout) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(sim.lock().unwrap().zoom(), 0x4000);
     }
 }
@@ -0,0 +1,529 @@
+//! Cue lists for running scripted shows across several cameras.
+//!
+//! A cue list file is plain text. Each cue starts with a `cue` line giving its number and
+//! an optional name, followed by the steps to take when it fires, one per line:
+//!
+//! ```text
+//! # visca cues
+//! cue 1 Walk-in
+//!   left recall 1
+//!   right recall 2 speed 0x10
+//! cue 2 Speaker
+//!   left move -400 120 speed 0x0c 0x0a
+//!   left +1.5s zoom 0x2000
+//!   right +500ms focus 0x1800
+//!   right +2s power off
+//! ```
+//!
+//! A step names the camera it applies to, then optionally how long after the cue fires
+//! it should happen, then the action. Steps for different cameras run at the same time.
+//! Numbers may be decimal or `0x` hex. Blank lines and lines starting with `#` are
+//! ignored.
+
+use crate::action::{perform, Action};
+use crate::commands::PanTiltValue;
+use crate::parse::{parse_duration, parse_number};
+use crate::{Camera, Error, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CueStep {
+    pub camera: String,
+    /// How long after the cue fires the action happens.
+    pub delay: Duration,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cue {
+    /// The cue's number, such as `1` or `2.5`, which is how `jump` finds it.
+    pub number: String,
+    pub name: String,
+    pub steps: Vec<CueStep>,
+}
+
+pub fn read_cue_list<R: BufRead>(reader: R) -> Result<Vec<Cue>> {
+    let mut cues: Vec<Cue> = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let invalid = || Error::InvalidCueList(i + 1);
+
+        match words.as_slice() {
+            [] => {}
+            [first, ..] if first.starts_with('#') => {}
+            ["cue", number, name @ ..] => cues.push(Cue {
+                number: number.to_string(),
+                name: name.join(" "),
+                steps: Vec::new(),
+            }),
+            words => {
+                let step = parse_step(words).ok_or_else(invalid)?;
+                cues.last_mut().ok_or_else(invalid)?.steps.push(step);
+            }
+        }
+    }
+
+    Ok(cues)
+}
+
+pub fn open_cue_list<P: AsRef<Path>>(path: P) -> Result<Vec<Cue>> {
+    let file = File::open(path)?;
+    read_cue_list(BufReader::new(file))
+}
+
+fn parse_step(words: &[&str]) -> Option<CueStep> {
+    let (camera, words) = words.split_first()?;
+    let (delay, words) = match words.split_first() {
+        Some((delay, rest)) if delay.starts_with('+') => (parse_duration(&delay[1..])?, rest),
+        _ => (Duration::from_secs(0), words),
+    };
+
+    let action = match words {
+        ["recall", preset] => Action::Recall {
+            preset: parse_number(preset)?,
+            speed: None,
+        },
+        ["recall", preset, "speed", speed] => Action::Recall {
+            preset: parse_number(preset)?,
+            speed: Some(parse_number(speed)?),
+        },
+        ["move", pan, tilt, rest @ ..] => Action::Move {
+            pan_tilt: PanTiltValue {
+                pan: parse_number(pan)?,
+                tilt: parse_number(tilt)?,
+            },
+            speed: match rest {
+                [] => None,
+                ["speed", pan, tilt] => Some((parse_number(pan)?, parse_number(tilt)?)),
+                _ => return None,
+            },
+        },
+        ["zoom", pos] => Action::Zoom {
+            position: parse_number(pos)?,
+            speed: None,
+        },
+        ["focus", pos] => Action::Focus(parse_number(pos)?),
+        ["power", "on"] => Action::Power(true),
+        ["power", "off"] => Action::Power(false),
+        _ => return None,
+    };
+
+    Some(CueStep {
+        camera: camera.to_string(),
+        delay,
+        action,
+    })
+}
+
+/// The steps of a fired cue that are still to come, which firing another cue cancels.
+struct Pending {
+    cancelled: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl Pending {
+    fn new() -> Self {
+        Pending {
+            cancelled: Mutex::new(false),
+            wake: Condvar::new(),
+        }
+    }
+
+    fn cancel(&self) {
+        *self.cancelled.lock().unwrap() = true;
+        self.wake.notify_all();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        *self.cancelled.lock().unwrap()
+    }
+
+    /// Sleeps until `deadline`, returning `false` if the cue is cancelled first.
+    fn wait_until(&self, deadline: Instant) -> bool {
+        let mut cancelled = self.cancelled.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            if *cancelled {
+                return false;
+            } else if now >= deadline {
+                return true;
+            }
+            cancelled = self.wake.wait_timeout(cancelled, deadline - now).unwrap().0;
+        }
+    }
+}
+
+/// Fires the cues of a cue list at a set of named cameras.
+///
+/// Cues fire in the background: `go`, `back` and `jump` return as soon as the cue's steps
+/// are under way, and `wait` waits for them to finish. Firing a cue cancels whatever steps
+/// of the previous one haven't happened yet, so that a delayed step can't undo the new
+/// cue. A step already being sent to its camera still completes.
+pub struct CueRunner {
+    cues: Vec<Cue>,
+    cameras: Vec<(String, Arc<Mutex<Camera>>)>,
+    position: Option<usize>,
+    pending: Arc<Pending>,
+    /// The threads of every cue fired since the last `wait`, cancelled or not.
+    threads: Vec<JoinHandle<Result<()>>>,
+    /// The first error from a thread that finished before `wait` got to it.
+    error: Option<Error>,
+}
+
+impl CueRunner {
+    pub fn new(cues: Vec<Cue>) -> Self {
+        CueRunner {
+            cues,
+            cameras: Vec::new(),
+            position: None,
+            pending: Arc::new(Pending::new()),
+            threads: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Adds the camera that steps naming `name` apply to.
+    pub fn add_camera(&mut self, name: &str, camera: Camera) {
+        self.cameras
+            .push((name.to_string(), Arc::new(Mutex::new(camera))));
+    }
+
+    pub fn camera(&self, name: &str) -> Option<MutexGuard<'_, Camera>> {
+        self.cameras
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, camera)| camera.lock().unwrap())
+    }
+
+    pub fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    /// Returns the cue that fired last.
+    pub fn current(&self) -> Option<&Cue> {
+        self.position.map(|i| &self.cues[i])
+    }
+
+    /// Fires the next cue, if there is one.
+    pub fn go(&mut self) -> Result<()> {
+        let next = self.position.map_or(0, |i| i + 1);
+        if next < self.cues.len() {
+            self.fire(next)?;
+        }
+        Ok(())
+    }
+
+    /// Fires the cue before the current one again, if there is one.
+    pub fn back(&mut self) -> Result<()> {
+        match self.position {
+            Some(i) if i > 0 => self.fire(i - 1),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fires the cue with the given number.
+    pub fn jump(&mut self, number: &str) -> Result<()> {
+        match self.cues.iter().position(|cue| cue.number == number) {
+            Some(i) => self.fire(i),
+            None => Err(Error::UnknownCue(number.to_string())),
+        }
+    }
+
+    /// Waits until every step of the cues fired since the last call has happened or been
+    /// cancelled, returning the first error a camera failed with in any of them, including
+    /// cues that were cut short by a later one.
+    pub fn wait(&mut self) -> Result<()> {
+        for thread in self.threads.drain(..) {
+            if let Err(err) = thread.join().expect("cue thread panicked") {
+                self.error.get_or_insert(err);
+            }
+        }
+
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn fire(&mut self, i: usize) -> Result<()> {
+        let steps = &self.cues[i].steps;
+        if let Some(step) = steps
+            .iter()
+            .find(|s| self.camera_index(&s.camera).is_none())
+        {
+            return Err(Error::UnknownCamera(step.camera.clone()));
+        }
+        self.position = Some(i);
+
+        // The previous cue's threads are left to notice they've been cancelled and exit,
+        // and are kept so that `wait` still reports their errors. Those that have already
+        // finished are reaped here so that they don't pile up between waits.
+        self.pending.cancel();
+        self.pending = Arc::new(Pending::new());
+        let (finished, running) = self.threads.drain(..).partition(|t| t.is_finished());
+        self.threads = running;
+        for thread in finished {
+            if let Err(err) = thread.join().expect("cue thread panicked") {
+                self.error.get_or_insert(err);
+            }
+        }
+
+        // Each camera works through its own steps in a thread of its own.
+        let started = Instant::now();
+        for (name, camera) in &self.cameras {
+            let mut steps: Vec<CueStep> = steps
+                .iter()
+                .filter(|s| &s.camera == name)
+                .cloned()
+                .collect();
+            if steps.is_empty() {
+                continue;
+            }
+            steps.sort_by_key(|s| s.delay);
+
+            let camera = camera.clone();
+            let pending = self.pending.clone();
+            self.threads.push(thread::spawn(move || {
+                for step in steps {
+                    if !pending.wait_until(started + step.delay) {
+                        break;
+                    }
+
+                    // A cue that fired while this one waited for the camera takes over.
+                    let mut camera = camera.lock().unwrap();
+                    if pending.is_cancelled() {
+                        break;
+                    }
+                    perform(&mut camera, step.action)?;
+                }
+                Ok(())
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn camera_index(&self, name: &str) -> Option<usize> {
+        self.cameras.iter().position(|(n, _)| n == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::ErrorKind;
+    use crate::testing::camera;
+
+    const CUES: &str = "# visca cues
+cue 1 Walk-in
+  left recall 1
+  right recall 2 speed 0x10
+
+cue 2 Speaker
+  left move -400 120 speed 0x0c 0x0a
+  left +1.5s zoom 0x2000
+  right +500ms focus 0x1800
+  right +2s power off
+";
+
+    #[test]
+    fn test_read_cue_list() {
+        let cues = read_cue_list(CUES.as_bytes()).unwrap();
+
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].number, "1");
+        assert_eq!(cues[0].name, "Walk-in");
+        assert_eq!(
+            cues[0].steps[1],
+            CueStep {
+                camera: "right".to_string(),
+                delay: Duration::from_secs(0),
+                action: Action::Recall {
+                    preset: 2,
+                    speed: Some(0x10),
+                },
+            }
+        );
+        assert_eq!(
+            cues[1].steps[0].action,
+            Action::Move {
+                pan_tilt: PanTiltValue {
+                    pan: -400,
+                    tilt: 120,
+                },
+                speed: Some((0x0c, 0x0a)),
+            }
+        );
+        assert_eq!(cues[1].steps[1].delay, Duration::from_millis(1500));
+        assert_eq!(cues[1].steps[2].action, Action::Focus(0x1800));
+        assert_eq!(cues[1].steps[3].action, Action::Power(false));
+    }
+
+    #[test]
+    fn test_invalid_cue_list() {
+        for (text, line) in &[
+            ("left recall 1\n", 1),
+            ("cue 1\n  left recall\n", 2),
+            ("cue 1\n\n  left +2 zoom 0x1000\n", 3),
+            ("cue 1\n  left zoom 0x10000\n", 2),
+        ] {
+            match read_cue_list(text.as_bytes()) {
+                Err(Error::InvalidCueList(n)) => assert_eq!(n, *line, "{:?}", text),
+                other => panic!("unexpected result for {:?}: {:?}", text, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_go_back_and_jump() {
+        let cues = read_cue_list(
+            "cue 1\n  cam zoom 0x1000\ncue 2\n  cam zoom 0x2000\ncue 3\n  cam zoom 0x3000\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let mut runner = CueRunner::new(cues);
+        runner.add_camera("cam", camera());
+
+        let zoom_target = |runner: &CueRunner| {
+            runner
+                .camera("cam")
+                .unwrap()
+                .zoom()
+                .wait_until_stopped(Duration::from_secs(5))
+                .unwrap()
+                .position
+        };
+
+        runner.go().unwrap();
+        runner.go().unwrap();
+        runner.wait().unwrap();
+        assert_eq!(runner.current().unwrap().number, "2");
+        assert_eq!(zoom_target(&runner), 0x2000);
+
+        runner.back().unwrap();
+        runner.wait().unwrap();
+        assert_eq!(runner.current().unwrap().number, "1");
+        assert_eq!(zoom_target(&runner), 0x1000);
+
+        runner.jump("3").unwrap();
+        runner.go().unwrap();
+        runner.wait().unwrap();
+        assert_eq!(runner.current().unwrap().number, "3");
+        assert_eq!(zoom_target(&runner), 0x3000);
+
+        match runner.jump("4") {
+            Err(Error::UnknownCue(number)) => assert_eq!(number, "4"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_delays_across_cameras() {
+        let cues = read_cue_list(
+            "cue 1\n  a +200ms zoom 0x1000\n  b +200ms zoom 0x2000\n  b zoom 0x800\n".as_bytes(),
+        )
+        .unwrap();
+        let mut runner = CueRunner::new(cues);
+        runner.add_camera("a", camera());
+        runner.add_camera("b", camera());
+
+        // The delayed step comes after the immediate one, whatever order they're listed in.
+        runner.go().unwrap();
+        runner.wait().unwrap();
+
+        for (name, target) in &[("a", 0x1000), ("b", 0x2000)] {
+            let zoom = runner
+                .camera(name)
+                .unwrap()
+                .zoom()
+                .wait_until_stopped(Duration::from_secs(5))
+                .unwrap();
+            assert_eq!(zoom.position, *target);
+        }
+    }
+
+    #[test]
+    fn test_next_cue_cancels_pending_steps() {
+        let cues = read_cue_list(
+            "cue 1\n  cam zoom 0x1000\n  cam +60s zoom 0x3000\ncue 2\n  cam focus 0x2000\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let mut runner = CueRunner::new(cues);
+        runner.add_camera("cam", camera());
+
+        // The first cue is still waiting on its delayed step when the second fires.
+        runner.go().unwrap();
+        assert_eq!(runner.current().unwrap().number, "1");
+        runner.go().unwrap();
+        runner.wait().unwrap();
+
+        let mut camera = runner.camera("cam").unwrap();
+        let focus = camera
+            .focus()
+            .wait_until_stopped(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(focus.position, 0x2000);
+        let zoom = camera
+            .zoom()
+            .wait_until_stopped(Duration::from_secs(5))
+            .unwrap();
+        assert_ne!(zoom.position, 0x3000);
+    }
+
+    #[test]
+    fn test_step_error() {
+        let cues = read_cue_list("cue 1\n  cam recall 1 speed 0x30\n".as_bytes()).unwrap();
+        let mut runner = CueRunner::new(cues);
+        runner.add_camera("cam", camera());
+
+        runner.go().unwrap();
+        match runner.wait() {
+            Err(Error::Camera(ErrorKind::Syntax)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_outlives_next_cue() {
+        let cues = read_cue_list(
+            "cue 1\n  cam recall 1 speed 0x30\ncue 2\n  cam zoom 0x1000\n".as_bytes(),
+        )
+        .unwrap();
+        let mut runner = CueRunner::new(cues);
+        runner.add_camera("cam", camera());
+
+        // The first cue has failed by the time the second fires.
+        runner.go().unwrap();
+        while !runner.threads.iter().all(|t| t.is_finished()) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        runner.go().unwrap();
+
+        match runner.wait() {
+            Err(Error::Camera(ErrorKind::Syntax)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        runner.wait().unwrap();
+    }
+
+    #[test]
+    fn test_unknown_camera() {
+        let cues = read_cue_list("cue 1\n  a zoom 0x1000\n  c zoom 0\n".as_bytes()).unwrap();
+        let mut runner = CueRunner::new(cues);
+        runner.add_camera("a", camera());
+
+        match runner.go() {
+            Err(Error::UnknownCamera(name)) => assert_eq!(name, "c"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(runner.current().is_none());
+    }
+}
@@ -0,0 +1,187 @@
+//! Finding VISCA-over-IP cameras on the local network.
+//!
+//! Sony cameras answer a broadcast `ENQ:network` search on UDP port 52380 with a list of
+//! `KEY:value` fields, each terminated by `FF`, between an STX (`02`) and an ETX (`03`).
+//! The search itself is framed the same way, and neither has anything after the ETX.
+
+use crate::ip::VISCA_IP_PORT;
+use crate::{Camera, Result};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::str;
+use std::time::{Duration, Instant};
+
+pub const DISCOVERY_PORT: u16 = 52380;
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+const TERMINATOR: u8 = 0xff;
+
+pub(crate) const ENQUIRY: &[u8] = b"\x02ENQ:network\xff\x03";
+
+/// A camera that answered a discovery search.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Candidate {
+    pub mac: String,
+    pub model: String,
+    pub ip: Ipv4Addr,
+    pub name: String,
+}
+
+impl Candidate {
+    /// Connects to the camera with VISCA-over-IP.
+    pub fn connect(&self) -> Result<Camera> {
+        Camera::connect_udp((self.ip, VISCA_IP_PORT))
+    }
+
+    #[cfg(any(test, feature = "sim"))]
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let fields = [
+            ("MAC", self.mac.clone()),
+            ("MODEL", self.model.clone()),
+            ("IPADR", self.ip.to_string()),
+            ("NAME", self.name.clone()),
+        ];
+
+        let mut bytes = vec![STX];
+        for (key, value) in &fields {
+            bytes.extend_from_slice(key.as_bytes());
+            bytes.push(b':');
+            bytes.extend_from_slice(value.as_bytes());
+            bytes.push(TERMINATOR);
+        }
+        bytes.push(ETX);
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let fields = match bytes {
+            [STX, fields @ .., ETX] => fields,
+            _ => return None,
+        };
+
+        let (mut mac, mut model, mut ip, mut name) = (None, String::new(), None, String::new());
+        for field in fields.split(|&b| b == TERMINATOR) {
+            let (key, value) = match str::from_utf8(field).ok().and_then(|f| f.split_once(':')) {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            match key {
+                "MAC" => mac = Some(value.to_string()),
+                "MODEL" => model = value.to_string(),
+                "IPADR" => ip = value.parse().ok(),
+                "NAME" => name = value.to_string(),
+                _ => {}
+            }
+        }
+
+        Some(Candidate {
+            mac: mac?,
+            model,
+            ip: ip?,
+            name,
+        })
+    }
+}
+
+/// Broadcasts a search on the local network and collects the cameras that answer within
+/// `timeout`.
+pub fn discover(timeout: Duration) -> Result<Vec<Candidate>> {
+    discover_at((Ipv4Addr::BROADCAST, DISCOVERY_PORT), timeout)
+}
+
+/// Sends a search to `addr`, which may be a broadcast address, and collects the cameras
+/// that answer within `timeout`. Each camera is reported once.
+pub fn discover_at<A: ToSocketAddrs>(addr: A, timeout: Duration) -> Result<Vec<Candidate>> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+    socket.set_broadcast(true)?;
+
+    let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+    for addr in &addrs {
+        socket.send_to(ENQUIRY, addr)?;
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut candidates: Vec<Candidate> = Vec::new();
+    let mut buf = [0; 1024];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let n = match socket.recv(&mut buf) {
+            Ok(n) => n,
+            Err(ref err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut =>
+            {
+                break
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if let Some(candidate) = Candidate::from_bytes(&buf[..n]) {
+            if !candidates.iter().any(|c| c.mac == candidate.mac) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::DiscoveryResponder;
+    use std::thread;
+
+    fn candidate(mac: &str, name: &str) -> Candidate {
+        Candidate {
+            mac: mac.to_string(),
+            model: "IPCARD".to_string(),
+            ip: Ipv4Addr::new(192, 168, 0, 100),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let bytes = b"\x02MAC:08-00-46-12-34-56\xffINFO:\xffMODEL:IPCARD\xff\
+                      SOFTVERSION:2.00\xffIPADR:192.168.0.100\xffMASK:255.255.255.0\xff\
+                      NAME:CAM1\xffWRITE:on\xff\x03";
+
+        assert_eq!(
+            Candidate::from_bytes(bytes),
+            Some(candidate("08-00-46-12-34-56", "CAM1"))
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_response() {
+        assert_eq!(Candidate::from_bytes(ENQUIRY), None);
+        assert_eq!(Candidate::from_bytes(b"\x02MAC:1\xff"), None);
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        let cam = candidate("08-00-46-12-34-56", "Stage left");
+        assert_eq!(Candidate::from_bytes(&cam.to_bytes()), Some(cam));
+    }
+
+    #[test]
+    fn test_discover_responder() {
+        let cam = candidate("08-00-46-12-34-56", "CAM1");
+        let responder = DiscoveryResponder::bind("127.0.0.1:0", cam.clone()).unwrap();
+        let addr = responder.local_addr().unwrap();
+        thread::spawn(move || responder.serve());
+
+        // Searching twice yields two answers from the same camera, reported only once.
+        let cameras = discover_at([addr, addr].as_ref(), Duration::from_millis(200)).unwrap();
+        assert_eq!(cameras, vec![cam]);
+    }
+}
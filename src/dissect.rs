@@ -0,0 +1,312 @@
+use crate::packet::{Category, Message, Reply};
+use crate::protocol::{Command, Direction, Inquiry};
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PacketKind {
+    Command,
+    Inquiry,
+    Ack,
+    Completion,
+    Error,
+    Notice,
+    Unknown,
+}
+
+/// A human-readable breakdown of a single VISCA packet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Description {
+    pub kind: PacketKind,
+    /// The address of the camera the packet was sent to or received from.
+    pub address: Option<u8>,
+    pub socket: Option<u8>,
+    pub name: String,
+    pub params: Vec<String>,
+}
+
+impl Description {
+    fn new(kind: PacketKind, address: Option<u8>, name: &str) -> Self {
+        Description {
+            kind,
+            address,
+            socket: None,
+            name: name.to_string(),
+            params: Vec::new(),
+        }
+    }
+
+    fn param<T: fmt::Display>(mut self, name: &str, value: T) -> Self {
+        self.params.push(format!("{} {}", name, value));
+        self
+    }
+}
+
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.address, self.socket) {
+            (Some(addr), Some(socket)) => write!(f, "[camera {}, socket {}] ", addr, socket)?,
+            (Some(addr), None) => write!(f, "[camera {}] ", addr)?,
+            _ => {}
+        }
+
+        write!(f, "{}", self.name)?;
+        for param in &self.params {
+            write!(f, ", {}", param)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Describes a complete packet, sent either by the controller or by a camera.
+pub fn dissect(bytes: &[u8]) -> Description {
+    match bytes.first() {
+        Some(b) if b & 0xf0 == 0x80 => dissect_request(bytes),
+        Some(b) if b & 0x80 != 0 => dissect_reply(bytes),
+        _ => malformed(bytes),
+    }
+}
+
+fn dissect_request(bytes: &[u8]) -> Description {
+    let address = match bytes[0] & 0x0f {
+        addr @ 1..=7 => Some(addr),
+        _ => None,
+    };
+
+    if let Some(cmd) = Command::decode(bytes) {
+        return describe_command(cmd, address);
+    }
+    if let Some(inq) = Inquiry::decode(bytes) {
+        return describe_inquiry(inq, address);
+    }
+
+    match bytes {
+        [_, msg @ 0x01, category, payload @ .., 0xff]
+        | [_, msg @ 0x09, category, payload @ .., 0xff] => {
+            let (kind, name) = if *msg == 0x01 {
+                (PacketKind::Command, "Unknown command")
+            } else {
+                (PacketKind::Inquiry, "Unknown inquiry")
+            };
+
+            Description::new(kind, address, name)
+                .param("category", category_name(*category))
+                .param("payload", hex(payload))
+        }
+        _ => malformed(bytes),
+    }
+}
+
+fn dissect_reply(bytes: &[u8]) -> Description {
+    let reply = match Reply::parse(bytes) {
+        Ok(reply) => reply,
+        Err(_) => return malformed(bytes),
+    };
+
+    let address = Some(reply.address());
+    let mut desc = match reply.message() {
+        Message::Ack => Description::new(PacketKind::Ack, address, "ACK"),
+        Message::Completion([]) => Description::new(PacketKind::Completion, address, "Completion"),
+        Message::Completion(payload) => {
+            Description::new(PacketKind::Completion, address, "Completion")
+                .param("payload", hex(payload))
+        }
+        Message::Error(kind) => {
+            Description::new(PacketKind::Error, address, "Error").param("reason", kind.as_str())
+        }
+        Message::NetworkChange => {
+            return Description::new(PacketKind::Notice, address, "Network Change");
+        }
+    };
+
+    desc.socket = Some(reply.socket());
+    desc
+}
+
+fn describe_command(cmd: Command, address: Option<u8>) -> Description {
+    let desc = |name| Description::new(PacketKind::Command, address, name);
+
+    match cmd {
+        Command::PanTiltDrive {
+            direction,
+            pan_speed,
+            tilt_speed,
+        } => desc(&format!("Pan-tilt Drive {}", direction_name(direction)))
+            .param("pan speed", pan_speed)
+            .param("tilt speed", tilt_speed),
+        Command::PanTiltAbsolute {
+            pan_speed,
+            tilt_speed,
+            position,
+        } => desc("Pan-tilt Absolute Position")
+            .param("pan speed", pan_speed)
+            .param("tilt speed", tilt_speed)
+            .param("pan", position.pan)
+            .param("tilt", position.tilt),
+        Command::PanTiltRelative {
+            pan_speed,
+            tilt_speed,
+            position,
+        } => desc("Pan-tilt Relative Position")
+            .param("pan speed", pan_speed)
+            .param("tilt speed", tilt_speed)
+            .param("pan", position.pan)
+            .param("tilt", position.tilt),
+        Command::PanTiltHome => desc("Pan-tilt Home"),
+        Command::ZoomDirect(val) => desc("Zoom Direct").param("position", format!("{:#06x}", val)),
+        Command::ZoomStop => desc("Zoom Stop"),
+        Command::ZoomTele(speed) => desc("Zoom Tele").param("speed", speed),
+        Command::ZoomWide(speed) => desc("Zoom Wide").param("speed", speed),
+        Command::FocusDirect(val) => {
+            desc("Focus Direct").param("position", format!("{:#06x}", val))
+        }
+        Command::FocusStop => desc("Focus Stop"),
+        Command::FocusFar(speed) => desc("Focus Far").param("speed", speed),
+        Command::FocusNear(speed) => desc("Focus Near").param("speed", speed),
+        Command::FocusAuto(true) => desc("Focus Auto"),
+        Command::FocusAuto(false) => desc("Focus Manual"),
+        Command::FocusOnePush => desc("Focus One Push Trigger"),
+        Command::PresetReset(num) => desc("Preset Reset").param("preset", num),
+        Command::PresetSet(num) => desc("Preset Set").param("preset", num),
+        Command::PresetRecall(num) => desc("Preset Recall").param("preset", num),
+        Command::PresetSpeed(speed) => desc("Preset Speed").param("speed", speed),
+        Command::Power(true) => desc("Power On"),
+        Command::Power(false) => desc("Power Off"),
+    }
+}
+
+fn describe_inquiry(inq: Inquiry, address: Option<u8>) -> Description {
+    let name = match inq {
+        Inquiry::PanTiltPosition => "Pan-tilt Position Inquiry",
+        Inquiry::ZoomPosition => "Zoom Position Inquiry",
+        Inquiry::FocusPosition => "Focus Position Inquiry",
+        Inquiry::Power => "Power Inquiry",
+        Inquiry::Version => "Version Inquiry",
+        Inquiry::LensBlock => "Lens Block Inquiry",
+        Inquiry::CameraBlock => "Camera Block Inquiry",
+        Inquiry::OtherBlock => "Other Block Inquiry",
+        Inquiry::EnlargementBlock => "Enlargement Block Inquiry",
+    };
+
+    Description::new(PacketKind::Inquiry, address, name)
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "Up",
+        Direction::Down => "Down",
+        Direction::Left => "Left",
+        Direction::Right => "Right",
+        Direction::UpLeft => "Up-Left",
+        Direction::UpRight => "Up-Right",
+        Direction::DownLeft => "Down-Left",
+        Direction::DownRight => "Down-Right",
+        Direction::Stop => "Stop",
+    }
+}
+
+fn category_name(b: u8) -> String {
+    match Category::from_u8(b) {
+        Some(Category::Interface) => "interface".to_string(),
+        Some(Category::Camera1) => "camera".to_string(),
+        Some(Category::PanTilter) => "pan-tilter".to_string(),
+        Some(Category::Vendor) => "vendor".to_string(),
+        None => format!("{:#04x}", b),
+    }
+}
+
+fn malformed(bytes: &[u8]) -> Description {
+    Description::new(PacketKind::Unknown, None, "Malformed packet").param("bytes", hex(bytes))
+}
+
+/// Formats bytes as space-separated uppercase hex pairs, e.g. `81 09 06 12 FF`.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses hex bytes, ignoring whitespace, e.g. `81 09 06 12 FF` or `81090612ff`.
+pub fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dissect_drive() {
+        let desc = dissect(&[0x81, 0x01, 0x06, 0x01, 0x18, 0x14, 0x03, 0x01, 0xff]);
+
+        assert_eq!(desc.kind, PacketKind::Command);
+        assert_eq!(desc.address, Some(1));
+        assert_eq!(
+            desc.to_string(),
+            "[camera 1] Pan-tilt Drive Up, pan speed 24, tilt speed 20"
+        );
+    }
+
+    #[test]
+    fn test_dissect_unknown_command() {
+        let desc = dissect(&[0x82, 0x01, 0x04, 0x10, 0x02, 0xff]);
+        assert_eq!(
+            desc.to_string(),
+            "[camera 2] Unknown command, category camera, payload 10 02"
+        );
+    }
+
+    #[test]
+    fn test_dissect_completion() {
+        let desc = dissect(&[0x90, 0x50, 0x00, 0x01, 0x0b, 0x0c, 0xff]);
+        assert_eq!(desc.socket, Some(0));
+        assert_eq!(
+            desc.to_string(),
+            "[camera 1, socket 0] Completion, payload 00 01 0B 0C"
+        );
+    }
+
+    #[test]
+    fn test_dissect_error() {
+        let desc = dissect(&[0x90, 0x61, 0x41, 0xff]);
+        assert_eq!(desc.kind, PacketKind::Error);
+        assert_eq!(
+            desc.to_string(),
+            "[camera 1, socket 1] Error, reason could not execute command"
+        );
+    }
+
+    #[test]
+    fn test_dissect_malformed() {
+        let desc = dissect(&[0x01, 0x02]);
+        assert_eq!(desc.kind, PacketKind::Unknown);
+        assert_eq!(desc.to_string(), "Malformed packet, bytes 01 02");
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(
+            parse_hex("81 09 06 12 FF"),
+            Some(vec![0x81, 0x09, 0x06, 0x12, 0xff])
+        );
+        assert_eq!(
+            parse_hex("8109 0612ff"),
+            Some(vec![0x81, 0x09, 0x06, 0x12, 0xff])
+        );
+        assert_eq!(parse_hex("81 0"), None);
+        assert_eq!(parse_hex("zz"), None);
+    }
+}
@@ -0,0 +1,527 @@
+use crate::interface::Interface;
+use crate::ip::{is_timeout, unwrap, wrap, PayloadType};
+use crate::packet::{Message, Reply, Request};
+use crate::{Camera, Error, Result};
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DATAGRAM_MAX_LEN: usize = 1024;
+
+/// How long a bus waits for a reply before checking for new requests to send.
+const BUS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// How long a camera has to acknowledge a request.
+const ACK_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long a camera has to complete a command it has acknowledged, such as a slow
+/// preset recall.
+const COMPLETION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Identifies a serial bus added to a `Gateway`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BusId(usize);
+
+struct Route {
+    socket: UdpSocket,
+    bus: BusId,
+    address: u8,
+}
+
+/// A request the gateway couldn't pass on to its camera, or whose reply it couldn't send
+/// back to the controller.
+#[derive(Debug)]
+pub struct ForwardError {
+    /// The controller that sent the request.
+    pub peer: SocketAddr,
+    /// The camera the request was routed to.
+    pub address: u8,
+    pub error: Error,
+}
+
+impl fmt::Display for ForwardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "request from {} to camera {} failed: {}",
+            self.peer, self.address, self.error
+        )
+    }
+}
+
+/// Makes cameras on serial buses reachable by controllers that speak VISCA-over-IP.
+///
+/// Each route listens on its own UDP socket and forwards the requests it receives to one
+/// camera address on a bus. VISCA-over-IP controllers always address the camera as 1, so
+/// the gateway rewrites the address on the way in and out.
+///
+/// Replies are passed on as soon as they arrive, and a bus only waits for a request to be
+/// acknowledged before sending the next one. A Stop therefore reaches the camera while a
+/// slow preset recall is still executing, as it would from a controller on the bus.
+#[derive(Default)]
+pub struct Gateway {
+    buses: Vec<Camera>,
+    routes: Vec<Route>,
+    errors: Option<Sender<ForwardError>>,
+}
+
+impl Gateway {
+    pub fn new() -> Self {
+        Gateway::default()
+    }
+
+    /// Adds a serial bus, represented by the `Camera` used to talk to it.
+    pub fn add_bus(&mut self, bus: Camera) -> BusId {
+        self.buses.push(bus);
+        BusId(self.buses.len() - 1)
+    }
+
+    /// Listens on `listen` and forwards requests to the camera at `address` on `bus`.
+    /// Returns the local address of the listening socket.
+    pub fn route<A: ToSocketAddrs>(
+        &mut self,
+        listen: A,
+        bus: BusId,
+        address: u8,
+    ) -> Result<SocketAddr> {
+        if !(1..=7).contains(&address) {
+            return Err(Error::InvalidAddress);
+        }
+
+        let socket = UdpSocket::bind(listen)?;
+        let local_addr = socket.local_addr()?;
+
+        self.routes.push(Route {
+            socket,
+            bus,
+            address,
+        });
+
+        Ok(local_addr)
+    }
+
+    /// Returns a channel that receives the requests that fail while the gateway runs.
+    /// Controllers are told about these too, so they only need reporting.
+    pub fn errors(&mut self) -> Receiver<ForwardError> {
+        let (tx, rx) = mpsc::channel();
+        self.errors = Some(tx);
+        rx
+    }
+
+    /// Serves every route until one of them fails.
+    pub fn run(self) -> io::Result<()> {
+        let errors = self.errors;
+        let buses: Vec<_> = self
+            .buses
+            .into_iter()
+            .map(|camera| {
+                let (requests, received) = mpsc::channel();
+                thread::spawn(move || serve_bus(camera.iface, received));
+                requests
+            })
+            .collect();
+
+        let handles: Vec<_> = self
+            .routes
+            .into_iter()
+            .map(|route| {
+                let bus = buses[route.bus.0].clone();
+                let errors = errors.clone();
+                thread::spawn(move || route.serve(bus, errors))
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().expect("gateway route panicked")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Route {
+    fn serve(&self, bus: Sender<Forward>, errors: Option<Sender<ForwardError>>) -> io::Result<()> {
+        let mut buf = [0; DATAGRAM_MAX_LEN];
+
+        loop {
+            let (n, peer) = self.socket.recv_from(&mut buf)?;
+
+            let (payload_type, seq, payload) = match unwrap(&buf[..n]) {
+                Some(unwrapped) => unwrapped,
+                None => continue,
+            };
+
+            match payload_type {
+                PayloadType::Command | PayloadType::Inquiry | PayloadType::DeviceSetting => {
+                    if let Some(forward) = self.forward(payload, seq, peer, &errors)? {
+                        bus.send(forward).map_err(|_| {
+                            io::Error::new(io::ErrorKind::BrokenPipe, "serial bus has stopped")
+                        })?;
+                    }
+                }
+                PayloadType::ControlCommand => {
+                    // The only control command is RESET, which clears the sequence number.
+                    let reply = wrap(PayloadType::ControlReply, seq, &[0x01]);
+                    self.socket.send_to(&reply, peer)?;
+                }
+                PayloadType::Reply | PayloadType::ControlReply => {}
+            }
+        }
+    }
+
+    fn forward(
+        &self,
+        payload: &[u8],
+        seq: u32,
+        peer: SocketAddr,
+        errors: &Option<Sender<ForwardError>>,
+    ) -> io::Result<Option<Forward>> {
+        // Broadcasts would reach every camera on the bus, not just the one we route to.
+        let mut bytes = payload.to_vec();
+        match bytes.first() {
+            Some(b) if b & 0xf0 == 0x80 && b & 0x0f != 0x08 => bytes[0] = 0x80 | self.address,
+            _ => return Ok(None),
+        }
+
+        let request = match Request::from_bytes(&bytes) {
+            Ok(request) => request,
+            Err(_) => return Ok(None),
+        };
+
+        Ok(Some(Forward {
+            request,
+            address: self.address,
+            socket: self.socket.try_clone()?,
+            peer,
+            seq,
+            errors: errors.clone(),
+        }))
+    }
+}
+
+/// A request received by a route, along with where to send the camera's replies.
+struct Forward {
+    request: Request,
+    address: u8,
+    socket: UdpSocket,
+    peer: SocketAddr,
+    seq: u32,
+    errors: Option<Sender<ForwardError>>,
+}
+
+impl Forward {
+    fn reply(&self, reply: &[u8]) {
+        let mut bytes = reply.to_vec();
+        bytes[0] = 0x90;
+
+        let datagram = wrap(PayloadType::Reply, self.seq, &bytes);
+        if let Err(err) = self.socket.send_to(&datagram, self.peer) {
+            self.report(Error::Io(err));
+        }
+    }
+
+    /// Tells the controller that the request failed, since it would otherwise wait for a
+    /// reply that never comes.
+    fn fail(&self, socket: u8, err: Error) {
+        self.report(err);
+        self.reply(&[0x90, 0x60 | socket, 0x41, 0xff]);
+    }
+
+    fn report(&self, error: Error) {
+        if let Some(errors) = &self.errors {
+            let _ = errors.send(ForwardError {
+                peer: self.peer,
+                address: self.address,
+                error,
+            });
+        }
+    }
+}
+
+/// A forwarded command that the camera has acknowledged but not yet completed.
+struct Executing {
+    forward: Forward,
+    socket: u8,
+    since: Instant,
+}
+
+/// Sends requests to the cameras on a bus and hands each reply to the request it answers.
+fn serve_bus(mut iface: Interface, requests: Receiver<Forward>) {
+    let _ = iface.set_read_timeout(BUS_POLL_INTERVAL);
+
+    // The request sent last, until the camera acknowledges or answers it.
+    let mut waiting: Option<(Forward, Instant)> = None;
+    let mut executing: Vec<Executing> = Vec::new();
+
+    loop {
+        if waiting.is_none() {
+            let next = if executing.is_empty() {
+                match requests.recv() {
+                    Ok(forward) => Some(forward),
+                    Err(_) => return,
+                }
+            } else {
+                match requests.try_recv() {
+                    Ok(forward) => Some(forward),
+                    Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+                }
+            };
+
+            if let Some(forward) = next {
+                match iface.send_request(&forward.request) {
+                    Ok(()) => waiting = Some((forward, Instant::now())),
+                    Err(err) => forward.fail(0, err),
+                }
+            }
+        }
+
+        let started = Instant::now();
+        match iface.recv_reply() {
+            Ok(reply) => dispatch(&reply, &mut waiting, &mut executing),
+            Err(Error::InvalidReply) => {}
+            Err(Error::Io(ref err)) if is_timeout(err) => {
+                // Some transports give up straight away when there is nothing to read.
+                thread::sleep(BUS_POLL_INTERVAL.saturating_sub(started.elapsed()));
+            }
+            Err(err) => {
+                if let Some((forward, _)) = waiting.take() {
+                    forward.fail(0, err);
+                }
+            }
+        }
+
+        if let Some((forward, _)) = waiting.take_if(|(_, since)| since.elapsed() > ACK_TIMEOUT) {
+            forward.fail(0, Error::NoReply);
+        }
+        executing.retain(|cmd| {
+            let expired = cmd.since.elapsed() > COMPLETION_TIMEOUT;
+            if expired {
+                cmd.forward.fail(cmd.socket, Error::NoReply);
+            }
+            !expired
+        });
+    }
+}
+
+fn dispatch(
+    reply: &Reply,
+    waiting: &mut Option<(Forward, Instant)>,
+    executing: &mut Vec<Executing>,
+) {
+    let address = reply.address();
+    let socket = reply.socket();
+
+    match reply.message() {
+        Message::NetworkChange => {}
+        Message::Ack => {
+            if let Some((forward, _)) = waiting.take_if(|(fwd, _)| fwd.address == address) {
+                forward.reply(reply.as_bytes());
+                executing.push(Executing {
+                    forward,
+                    socket,
+                    since: Instant::now(),
+                });
+            }
+        }
+        Message::Completion(_) | Message::Error(_) => {
+            // Completions and errors for executing commands carry the socket they were
+            // acknowledged with, while those for the latest request come straight back.
+            let pos = executing
+                .iter()
+                .position(|cmd| cmd.forward.address == address && cmd.socket == socket);
+
+            if let (Some(pos), true) = (pos, socket != 0) {
+                executing.remove(pos).forward.reply(reply.as_bytes());
+            } else if let Some((forward, _)) = waiting.take_if(|(fwd, _)| fwd.address == address) {
+                forward.reply(reply.as_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::transport::Transport;
+    use std::collections::VecDeque;
+
+    fn spawn(gateway: Gateway) {
+        thread::spawn(move || gateway.run());
+    }
+
+    fn client() -> UdpSocket {
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        client
+    }
+
+    fn recv(client: &UdpSocket) -> Vec<u8> {
+        let mut buf = [0; 64];
+        let n = client.recv(&mut buf).unwrap();
+        buf[..n].to_vec()
+    }
+
+    /// A camera that acknowledges a preset recall straight away but only completes it
+    /// once another command has been sent, as if that command had found it still moving.
+    #[derive(Default)]
+    struct SlowRecall {
+        replies: VecDeque<u8>,
+        held: Option<Vec<u8>>,
+    }
+
+    impl Transport for SlowRecall {
+        fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+            if packet[1..] == [0x01, 0x04, 0x3f, 0x02, 0x01, 0xff] {
+                self.replies.extend(&[0x90, 0x41, 0xff]);
+                self.held = Some(vec![0x90, 0x51, 0xff]);
+            } else {
+                self.replies.extend(&[0x90, 0x42, 0xff, 0x90, 0x52, 0xff]);
+                self.replies.extend(self.held.take().unwrap_or_default());
+            }
+            Ok(())
+        }
+
+        fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.replies.len().min(buf.len());
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no reply"));
+            }
+            for (byte, reply) in buf.iter_mut().zip(self.replies.drain(..n)) {
+                *byte = reply;
+            }
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_forward_to_serial_address() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x82, 0x09, 0x04, 0x47, 0xff])
+            .reply(&[0xa0, 0x50, 0x01, 0x02, 0x03, 0x04, 0xff]);
+        mock.expect(&[0x82, 0x01, 0x04, 0x3f, 0x02, 0x01, 0xff])
+            .reply(&[0xa0, 0x41, 0xff])
+            .reply(&[0xa0, 0x51, 0xff]);
+
+        let mut gateway = Gateway::new();
+        let bus = gateway.add_bus(Camera::with_transport(mock));
+        let addr = gateway.route("127.0.0.1:0", bus, 2).unwrap();
+        spawn(gateway);
+
+        let mut camera = Camera::connect_udp(addr).unwrap();
+        assert_eq!(camera.zoom().get().unwrap(), 0x1234);
+        camera.presets().recall(1).unwrap();
+    }
+
+    #[test]
+    fn test_replies_carry_request_sequence_number() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x01, 0x06, 0x04, 0xff])
+            .reply(&[0x90, 0x41, 0xff])
+            .reply(&[0x90, 0x51, 0xff]);
+
+        let mut gateway = Gateway::new();
+        let bus = gateway.add_bus(Camera::with_transport(mock));
+        let addr = gateway.route("127.0.0.1:0", bus, 1).unwrap();
+        spawn(gateway);
+
+        let client = client();
+        let reset = wrap(PayloadType::ControlCommand, 0, &[0x01]);
+        client.send_to(&reset, addr).unwrap();
+
+        let home = wrap(PayloadType::Command, 42, &[0x81, 0x01, 0x06, 0x04, 0xff]);
+        client.send_to(&home, addr).unwrap();
+
+        let mut buf = [0; 64];
+        let mut datagrams = Vec::new();
+        for _ in 0..3 {
+            let n = client.recv(&mut buf).unwrap();
+            datagrams.push(buf[..n].to_vec());
+        }
+
+        assert_eq!(
+            datagrams,
+            vec![
+                wrap(PayloadType::ControlReply, 0, &[0x01]),
+                wrap(PayloadType::Reply, 42, &[0x90, 0x41, 0xff]),
+                wrap(PayloadType::Reply, 42, &[0x90, 0x51, 0xff]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commands_overlap_on_bus() {
+        let mut gateway = Gateway::new();
+        let bus = gateway.add_bus(Camera::with_transport(SlowRecall::default()));
+        let recall_addr = gateway.route("127.0.0.1:0", bus, 1).unwrap();
+        let stop_addr = gateway.route("127.0.0.1:0", bus, 1).unwrap();
+        spawn(gateway);
+
+        // The recall is acknowledged before it completes.
+        let recaller = client();
+        let recall = wrap(
+            PayloadType::Command,
+            1,
+            &[0x81, 0x01, 0x04, 0x3f, 0x02, 0x01, 0xff],
+        );
+        recaller.send_to(&recall, recall_addr).unwrap();
+        assert_eq!(
+            recv(&recaller),
+            wrap(PayloadType::Reply, 1, &[0x90, 0x41, 0xff])
+        );
+
+        // Another controller's Stop isn't held up behind it.
+        let stopper = client();
+        let stop = wrap(
+            PayloadType::Command,
+            7,
+            &[0x81, 0x01, 0x06, 0x01, 0x01, 0x01, 0x03, 0x03, 0xff],
+        );
+        stopper.send_to(&stop, stop_addr).unwrap();
+        assert_eq!(
+            recv(&stopper),
+            wrap(PayloadType::Reply, 7, &[0x90, 0x42, 0xff])
+        );
+        assert_eq!(
+            recv(&stopper),
+            wrap(PayloadType::Reply, 7, &[0x90, 0x52, 0xff])
+        );
+
+        assert_eq!(
+            recv(&recaller),
+            wrap(PayloadType::Reply, 1, &[0x90, 0x51, 0xff])
+        );
+    }
+
+    #[test]
+    fn test_no_reply_is_reported_to_controller() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x01, 0x06, 0x04, 0xff]);
+
+        let mut gateway = Gateway::new();
+        let bus = gateway.add_bus(Camera::with_transport(mock));
+        let addr = gateway.route("127.0.0.1:0", bus, 1).unwrap();
+        let errors = gateway.errors();
+        spawn(gateway);
+
+        let client = client();
+        let home = wrap(PayloadType::Command, 3, &[0x81, 0x01, 0x06, 0x04, 0xff]);
+        client.send_to(&home, addr).unwrap();
+
+        assert_eq!(
+            recv(&client),
+            wrap(PayloadType::Reply, 3, &[0x90, 0x60, 0x41, 0xff])
+        );
+
+        let failure = errors.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(failure.peer, client.local_addr().unwrap());
+        assert_eq!(failure.address, 1);
+        match failure.error {
+            Error::NoReply => {}
+            err => panic!("expected NoReply, got {:?}", err),
+        }
+    }
+}
@@ -1,86 +1,189 @@
+use crate::capture::{Flow, Recorder};
+use crate::ip::UdpTransport;
 use crate::packet::{Message, Reply, Request};
+use crate::tcp::TcpTransport;
+use crate::transport::{SerialTransport, Transport};
 use crate::{Error, Result};
 use serialport::prelude::*;
 use std::io;
+use std::net::ToSocketAddrs;
 use std::path::Path;
 use std::time::Duration;
 
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+
 pub struct Interface {
-    port: Box<dyn SerialPort>,
+    transport: Box<dyn Transport>,
+    recorder: Option<Recorder>,
+    recording_error: Option<Error>,
     rbuf: [u8; 16],
     rlen: usize,
 }
 
 impl Interface {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Interface::open_with_baud_rate(path, DEFAULT_BAUD_RATE)
+    }
+
+    pub fn open_with_baud_rate<P: AsRef<Path>>(path: P, baud_rate: u32) -> Result<Self> {
+        Interface::open_with_timeout(path, baud_rate, Duration::from_secs(1))
+    }
+
+    pub(crate) fn open_with_timeout<P: AsRef<Path>>(
+        path: P,
+        baud_rate: u32,
+        timeout: Duration,
+    ) -> Result<Self> {
         let settings = SerialPortSettings {
-            baud_rate: 9600,
+            baud_rate,
             data_bits: DataBits::Eight,
             flow_control: FlowControl::None,
             parity: Parity::None,
             stop_bits: StopBits::One,
-            timeout: Duration::from_secs(1),
+            timeout,
         };
 
         serialport::open_with_settings(path.as_ref(), &settings)
-            .map(|port| Interface::new(port))
+            .map(|port| Interface::new(Box::new(SerialTransport::new(port))))
             .map_err(|err| Error::Io(err.into()))
     }
 
-    fn new(port: Box<dyn SerialPort>) -> Self {
+    pub fn connect_udp<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let transport = UdpTransport::connect(addr)?;
+        Ok(Interface::new(Box::new(transport)))
+    }
+
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A, timeout: Duration) -> Result<Self> {
+        let transport = TcpTransport::connect(addr, timeout)?;
+        Ok(Interface::new(Box::new(transport)))
+    }
+
+    pub fn new(transport: Box<dyn Transport>) -> Self {
         Interface {
-            port,
+            transport,
+            recorder: None,
+            recording_error: None,
             rbuf: [0; 16],
             rlen: 0,
         }
     }
 
+    /// Starts or stops recording the packets sent and received through this interface.
+    pub fn set_recorder(&mut self, recorder: Option<Recorder>) {
+        self.recorder = recorder;
+        self.recording_error = None;
+    }
+
+    pub(crate) fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.transport.set_read_timeout(timeout)
+    }
+
+    /// Returns whether packets are being recorded. Recording stops by itself if the
+    /// capture file can't be written to.
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Returns the write error that stopped the last recording, if any.
+    pub fn recording_error(&self) -> Option<&Error> {
+        self.recording_error.as_ref()
+    }
+
+    /// Sends a request and waits for its completion, skipping over the ACK if there is one.
+    ///
+    /// Network change notices and corrupted packets are discarded, so that a glitch on the
+    /// line doesn't leave the interface out of step with the camera.
     pub fn send_request_with_reply(&mut self, req: &Request) -> Result<Reply> {
+        self.send_request_with_replies(req, |_| {})
+    }
+
+    /// Like `send_request_with_reply`, but also passes every ACK, completion and error
+    /// received for the request to `each` as it arrives.
+    pub fn send_request_with_replies<F>(&mut self, req: &Request, mut each: F) -> Result<Reply>
+    where
+        F: FnMut(&Reply),
+    {
         self.send_request(req)?;
 
-        self.recv_reply().and_then(|reply| match reply.message() {
-            Message::Ack => self.recv_reply().and_then(|reply| match reply.message() {
-                Message::Completion(_) => Ok(reply),
-                Message::Error(err) => Err(Error::Camera(err)),
-                _ => Err(Error::InvalidReply),
-            }),
-            Message::Completion(_) => Ok(reply),
-            Message::Error(err) => Err(Error::Camera(err)),
-        })
+        let mut acked = false;
+        loop {
+            let reply = match self.recv_reply() {
+                Err(Error::InvalidReply) => continue,
+                result => result?,
+            };
+
+            if reply.message() != Message::NetworkChange {
+                each(&reply);
+            }
+
+            match reply.message() {
+                Message::Ack if !acked => acked = true,
+                Message::Ack => return Err(Error::InvalidReply),
+                Message::Completion(_) => return Ok(reply),
+                Message::Error(err) => return Err(Error::Camera(err)),
+                Message::NetworkChange => continue,
+            }
+        }
     }
 
     pub fn send_request(&mut self, req: &Request) -> Result<()> {
-        self.port
-            .write_all(req.as_bytes())
-            .map_err(|err| err.into())
+        self.transport.send(req.as_bytes())?;
+        self.record(Flow::Sent, req.as_bytes());
+        Ok(())
     }
 
     pub fn recv_reply(&mut self) -> Result<Reply> {
-        if let Some(reply) = self.extract_reply() {
-            return Ok(reply);
+        let packet = self.recv_packet()?;
+        Reply::parse(&packet)
+    }
+
+    /// Receives a packet without parsing it, for broadcast replies that don't follow the
+    /// usual reply format.
+    pub(crate) fn recv_packet(&mut self) -> Result<Vec<u8>> {
+        let packet = self.read_packet()?;
+        self.record(Flow::Received, &packet);
+        Ok(packet)
+    }
+
+    /// Records a packet that has already gone out or come in. A capture is only there to
+    /// help diagnose problems, so failing to write one must not fail the request.
+    fn record(&mut self, flow: Flow, bytes: &[u8]) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.record(flow, bytes) {
+                self.recorder = None;
+                self.recording_error = Some(err);
+            }
+        }
+    }
+
+    fn read_packet(&mut self) -> Result<Vec<u8>> {
+        if let Some(packet) = self.extract_packet() {
+            return Ok(packet);
         }
 
         loop {
-            match self.port.read(&mut self.rbuf[self.rlen..]) {
+            match self.transport.recv(&mut self.rbuf[self.rlen..]) {
                 Ok(n) => {
                     self.rlen += n;
 
-                    if let Some(reply) = self.extract_reply() {
-                        return Ok(reply);
+                    if let Some(packet) = self.extract_packet() {
+                        return Ok(packet);
                     } else if self.rbuf_full() {
+                        // Without a terminator in sight the buffer can only hold garbage.
+                        self.rlen = 0;
                         return Err(Error::ReadBufferFull);
                     }
                 }
                 Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                Err(err) => return Err(Error::Io(err)),
+                Err(err) => return Err(err.into()),
             }
         }
     }
 
-    fn extract_reply(&mut self) -> Option<Reply> {
+    fn extract_packet(&mut self) -> Option<Vec<u8>> {
         memchr::memchr(0xff, &self.rbuf[..self.rlen]).map(|pos| {
             let end = pos + 1;
-            let packet = Reply::parse(&self.rbuf[..end]);
+            let packet = self.rbuf[..end].to_vec();
             self.rlen -= end;
 
             if self.rlen > 0 {
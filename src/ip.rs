@@ -0,0 +1,421 @@
+use crate::transport::Transport;
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+pub const VISCA_IP_PORT: u16 = 52381;
+
+const HEADER_LEN: usize = 8;
+const DATAGRAM_MAX_LEN: usize = 1024;
+
+#[repr(u16)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PayloadType {
+    Command = 0x0100,
+    Inquiry = 0x0110,
+    Reply = 0x0111,
+    DeviceSetting = 0x0120,
+    ControlCommand = 0x0200,
+    ControlReply = 0x0201,
+}
+
+impl PayloadType {
+    pub fn from_u16(n: u16) -> Option<Self> {
+        match n {
+            0x0100 => Some(PayloadType::Command),
+            0x0110 => Some(PayloadType::Inquiry),
+            0x0111 => Some(PayloadType::Reply),
+            0x0120 => Some(PayloadType::DeviceSetting),
+            0x0200 => Some(PayloadType::ControlCommand),
+            0x0201 => Some(PayloadType::ControlReply),
+            _ => None,
+        }
+    }
+
+    /// Picks the payload type for a VISCA request based on its message type byte.
+    fn for_request(packet: &[u8]) -> Self {
+        match packet.get(1) {
+            Some(0x09) => PayloadType::Inquiry,
+            _ => PayloadType::Command,
+        }
+    }
+}
+
+/// Prepends a VISCA-over-IP header to `payload`.
+pub fn wrap(payload_type: PayloadType, seq: u32, payload: &[u8]) -> Vec<u8> {
+    let mut datagram = Vec::with_capacity(HEADER_LEN + payload.len());
+    datagram.extend_from_slice(&(payload_type as u16).to_be_bytes());
+    datagram.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    datagram.extend_from_slice(&seq.to_be_bytes());
+    datagram.extend_from_slice(payload);
+    datagram
+}
+
+/// Splits a VISCA-over-IP datagram into its payload type, sequence number and payload.
+pub fn unwrap(datagram: &[u8]) -> Option<(PayloadType, u32, &[u8])> {
+    if datagram.len() < HEADER_LEN {
+        return None;
+    }
+
+    let payload_type = PayloadType::from_u16(u16::from_be_bytes([datagram[0], datagram[1]]))?;
+    let len = u16::from_be_bytes([datagram[2], datagram[3]]) as usize;
+    let seq = u32::from_be_bytes([datagram[4], datagram[5], datagram[6], datagram[7]]);
+
+    let payload = &datagram[HEADER_LEN..];
+    if payload.len() != len {
+        return None;
+    }
+
+    Some((payload_type, seq, payload))
+}
+
+/// How long to wait for the first reply to a request before sending it again.
+const RETRANSMIT_TIMEOUT: Duration = Duration::from_millis(200);
+const MAX_RETRANSMITS: usize = 3;
+/// How long to wait for the completion once the camera has replied to a request.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+const RESET: &[u8] = &[0x01];
+const SEQUENCE_ERROR: &[u8] = &[0x0f, 0x01];
+const MESSAGE_ERROR: &[u8] = &[0x0f, 0x02];
+
+/// Failures of the VISCA-over-IP link, carried inside an `io::Error` so that they can be
+/// told apart from other I/O errors.
+#[derive(Debug)]
+pub(crate) enum LinkError {
+    /// The camera never replied, even after the request was retransmitted.
+    NoReply,
+    /// The camera reported that the request had an invalid length or payload type.
+    MessageRejected,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::NoReply => write!(f, "no reply after {} retransmissions", MAX_RETRANSMITS),
+            LinkError::MessageRejected => write!(f, "camera rejected the message"),
+        }
+    }
+}
+
+impl error::Error for LinkError {}
+
+/// Speaks Sony's VISCA-over-IP protocol to a single camera over UDP.
+///
+/// Requests the camera hasn't replied to are retransmitted, and the sequence number is
+/// reset if the camera reports that it is out of step.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    /// The sequence number of the next request.
+    seq: u32,
+    /// The sequence number of the last request sent, which its replies carry.
+    current_seq: u32,
+    /// The last request sent, until the camera replies to it.
+    pending: Option<Vec<u8>>,
+    retransmits: usize,
+}
+
+impl UdpTransport {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(addr)?;
+
+        Ok(UdpTransport {
+            socket,
+            seq: 0,
+            current_seq: 0,
+            pending: None,
+            retransmits: 0,
+        })
+    }
+
+    fn transmit(&mut self, packet: &[u8]) -> io::Result<()> {
+        let datagram = wrap(PayloadType::for_request(packet), self.current_seq, packet);
+        self.socket.send(&datagram).map(|_| ())
+    }
+
+    /// Sends the pending request again, with the same sequence number.
+    fn retransmit(&mut self) -> io::Result<()> {
+        if self.retransmits == MAX_RETRANSMITS {
+            self.pending = None;
+            return Err(io::Error::new(io::ErrorKind::TimedOut, LinkError::NoReply));
+        }
+        self.retransmits += 1;
+
+        if let Some(packet) = self.pending.take() {
+            self.transmit(&packet)?;
+            self.pending = Some(packet);
+        }
+        Ok(())
+    }
+
+    /// Resets the camera's sequence number and resends the pending request from zero.
+    fn reset_sequence(&mut self) -> io::Result<()> {
+        self.socket
+            .send(&wrap(PayloadType::ControlCommand, 0, RESET))?;
+        self.current_seq = 0;
+        self.seq = 1;
+
+        // Counting the reset as a retransmission stops a camera that keeps rejecting our
+        // sequence numbers from holding us here forever.
+        self.retransmit()
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.current_seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        self.pending = Some(packet.to_vec());
+        self.retransmits = 0;
+        self.transmit(packet)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut datagram = [0; DATAGRAM_MAX_LEN];
+        // Datagrams that aren't for us are skipped without extending the wait.
+        let mut deadline = None;
+
+        loop {
+            let timeout = if self.pending.is_some() {
+                RETRANSMIT_TIMEOUT
+            } else {
+                READ_TIMEOUT
+            };
+            let until = *deadline.get_or_insert_with(|| Instant::now() + timeout);
+            let left = until.saturating_duration_since(Instant::now());
+
+            let received = if left.is_zero() {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "no reply"))
+            } else {
+                self.socket.set_read_timeout(Some(left))?;
+                self.socket.recv(&mut datagram)
+            };
+
+            let n = match received {
+                Ok(n) => n,
+                Err(ref err) if is_timeout(err) && self.pending.is_some() => {
+                    self.retransmit()?;
+                    deadline = None;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            match unwrap(&datagram[..n]) {
+                Some((PayloadType::Reply, seq, payload)) if seq == self.current_seq => {
+                    self.pending = None;
+
+                    let len = payload.len().min(buf.len());
+                    buf[..len].copy_from_slice(&payload[..len]);
+                    return Ok(len);
+                }
+                Some((PayloadType::ControlReply, _, SEQUENCE_ERROR)) => {
+                    self.reset_sequence()?;
+                    deadline = None;
+                }
+                Some((PayloadType::ControlReply, _, MESSAGE_ERROR)) => {
+                    self.pending = None;
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        LinkError::MessageRejected,
+                    ));
+                }
+                // Late replies to earlier requests, acknowledgements of resets, and anything
+                // else that isn't a reply to this request.
+                _ => continue,
+            }
+        }
+    }
+}
+
+pub(crate) fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Camera, Error};
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    type Received = Arc<Mutex<Vec<(PayloadType, u32, Vec<u8>)>>>;
+
+    /// Stands in for a camera on an unreliable network. `respond` is called with the index
+    /// of each datagram received and returns the datagrams to send back, if any.
+    fn stand_in<F>(mut respond: F) -> (SocketAddr, Received)
+    where
+        F: FnMut(usize, PayloadType, u32, &[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let received = Received::default();
+
+        let log = received.clone();
+        thread::spawn(move || {
+            let mut buf = [0; DATAGRAM_MAX_LEN];
+            for i in 0.. {
+                let (n, peer) = socket.recv_from(&mut buf).unwrap();
+                let (payload_type, seq, payload) = unwrap(&buf[..n]).unwrap();
+                log.lock()
+                    .unwrap()
+                    .push((payload_type, seq, payload.to_vec()));
+
+                for datagram in respond(i, payload_type, seq, payload) {
+                    socket.send_to(&datagram, peer).unwrap();
+                }
+            }
+        });
+
+        (addr, received)
+    }
+
+    fn completion(seq: u32) -> Vec<Vec<u8>> {
+        vec![
+            wrap(PayloadType::Reply, seq, &[0x90, 0x41, 0xff]),
+            wrap(PayloadType::Reply, seq, &[0x90, 0x51, 0xff]),
+        ]
+    }
+
+    const HOME: &[u8] = &[0x81, 0x01, 0x06, 0x04, 0xff];
+
+    #[test]
+    fn test_retransmit_dropped_request() {
+        let (addr, received) = stand_in(|i, _, seq, _| match i {
+            0 => vec![],
+            _ => completion(seq),
+        });
+
+        let mut camera = Camera::connect_udp(addr).unwrap();
+        camera.pan_tilt().home().unwrap();
+        camera.pan_tilt().home().unwrap();
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                (PayloadType::Command, 0, HOME.to_vec()),
+                (PayloadType::Command, 0, HOME.to_vec()),
+                (PayloadType::Command, 1, HOME.to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignore_stale_replies() {
+        let (addr, _) = stand_in(|i, _, seq, _| match i {
+            // Answer the retransmission as well as the original request, late.
+            0 => vec![],
+            1 => [completion(seq), completion(seq)].concat(),
+            _ => vec![wrap(PayloadType::Reply, seq, &[0x90, 0x50, 0x02, 0xff])],
+        });
+
+        let mut camera = Camera::connect_udp(addr).unwrap();
+        camera.pan_tilt().home().unwrap();
+        assert!(camera.power().get().unwrap());
+    }
+
+    #[test]
+    fn test_skip_unexpected_datagrams() {
+        let (addr, _) = stand_in(|_, _, seq, _| {
+            let mut datagrams = vec![
+                vec![0x01, 0x02, 0x03],
+                wrap(PayloadType::Command, seq, HOME),
+                wrap(PayloadType::Reply, seq.wrapping_sub(1), &[0x90, 0x51, 0xff]),
+            ];
+            datagrams.extend(completion(seq));
+            datagrams
+        });
+
+        let mut camera = Camera::connect_udp(addr).unwrap();
+        camera.pan_tilt().home().unwrap();
+        camera.pan_tilt().home().unwrap();
+    }
+
+    #[test]
+    fn test_reset_on_sequence_error() {
+        let (addr, received) = stand_in(|i, payload_type, seq, _| match (i, payload_type) {
+            (0, _) => vec![wrap(PayloadType::ControlReply, seq, SEQUENCE_ERROR)],
+            (_, PayloadType::ControlCommand) => vec![wrap(PayloadType::ControlReply, seq, RESET)],
+            _ => completion(seq),
+        });
+
+        let mut camera = Camera::connect_udp(addr).unwrap();
+        camera.pan_tilt().home().unwrap();
+        camera.pan_tilt().home().unwrap();
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                (PayloadType::Command, 0, HOME.to_vec()),
+                (PayloadType::ControlCommand, 0, RESET.to_vec()),
+                (PayloadType::Command, 0, HOME.to_vec()),
+                (PayloadType::Command, 1, HOME.to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_error() {
+        let (addr, _) =
+            stand_in(|_, _, seq, _| vec![wrap(PayloadType::ControlReply, seq, MESSAGE_ERROR)]);
+
+        let mut camera = Camera::connect_udp(addr).unwrap();
+        match camera.pan_tilt().home() {
+            Err(Error::InvalidRequest) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_no_reply() {
+        let (addr, received) = stand_in(|_, _, _, _| vec![]);
+
+        let mut camera = Camera::connect_udp(addr).unwrap();
+        match camera.pan_tilt().home() {
+            Err(Error::NoReply) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(received.lock().unwrap().len(), 1 + MAX_RETRANSMITS);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let datagram = wrap(
+            PayloadType::Inquiry,
+            0x0102,
+            &[0x81, 0x09, 0x04, 0x47, 0xff],
+        );
+
+        assert_eq!(
+            datagram,
+            &[0x01, 0x10, 0x00, 0x05, 0x00, 0x00, 0x01, 0x02, 0x81, 0x09, 0x04, 0x47, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_unwrap() {
+        let datagram = &[
+            0x01, 0x11, 0x00, 0x03, 0x00, 0x00, 0x00, 0x07, 0x90, 0x41, 0xff,
+        ];
+
+        assert_eq!(
+            unwrap(datagram),
+            Some((PayloadType::Reply, 7, &[0x90, 0x41, 0xff][..]))
+        );
+    }
+
+    #[test]
+    fn test_unwrap_bad_length() {
+        let datagram = &[
+            0x01, 0x11, 0x00, 0x04, 0x00, 0x00, 0x00, 0x07, 0x90, 0x41, 0xff,
+        ];
+        assert_eq!(unwrap(datagram), None);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::commands::Zoom;
+use crate::models::Model;
+use crate::{Error, Result};
+
+/// Relates a model's raw zoom positions to magnification and field of view.
+///
+/// Magnification is interpolated linearly between the points of the table, which should
+/// cover digital zoom too if the model has it. The field of view follows from the
+/// magnification and the horizontal field of view at the wide end.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoomTable {
+    points: Vec<(u16, f64)>,
+    wide_hfov: f64,
+    aspect_ratio: f64,
+}
+
+/// An angle of view in degrees.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FieldOfView {
+    pub horizontal: f64,
+    pub vertical: f64,
+}
+
+impl ZoomTable {
+    /// Creates a table from `(position, magnification)` points, which must be sorted and
+    /// strictly increasing in both. `wide_hfov` is the horizontal field of view in
+    /// degrees at the first point, and `aspect_ratio` the picture's width over its height.
+    pub fn new(points: Vec<(u16, f64)>, wide_hfov: f64, aspect_ratio: f64) -> Result<Self> {
+        let increasing = points
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1);
+        let valid = points.len() >= 2
+            && increasing
+            && points[0].1 > 0.0
+            && wide_hfov > 0.0
+            && wide_hfov < 180.0
+            && aspect_ratio > 0.0;
+
+        if !valid {
+            return Err(Error::InvalidCalibration);
+        }
+
+        Ok(ZoomTable {
+            points,
+            wide_hfov,
+            aspect_ratio,
+        })
+    }
+
+    /// Returns the zoom table of the model that reports `model_id`, if it's known.
+    pub fn for_model(model_id: u16) -> Option<Self> {
+        Model::for_id(model_id).map(Model::zoom_table)
+    }
+
+    /// Returns the magnification at a raw zoom position, relative to the wide end.
+    /// Positions beyond the table are clamped to its ends.
+    pub fn magnification(&self, position: u16) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if position <= first.0 {
+            return 1.0;
+        }
+        if position >= last.0 {
+            return last.1 / first.1;
+        }
+
+        let i = self.points.iter().position(|p| p.0 > position).unwrap();
+        let (p0, p1) = (self.points[i - 1], self.points[i]);
+        let t = (position - p0.0) as f64 / (p1.0 - p0.0) as f64;
+        (p0.1 + t * (p1.1 - p0.1)) / first.1
+    }
+
+    /// Returns the raw zoom position that gives a magnification, failing with
+    /// `Error::OutOfRange` if the lens can't reach it.
+    pub fn position(&self, magnification: f64) -> Result<u16> {
+        let m = magnification * self.points[0].1;
+        let i = match self.points.iter().position(|p| p.1 >= m) {
+            Some(0) if m == self.points[0].1 => return Ok(self.points[0].0),
+            Some(0) | None => return Err(Error::OutOfRange),
+            Some(i) => i,
+        };
+
+        let (p0, p1) = (self.points[i - 1], self.points[i]);
+        let t = (m - p0.1) / (p1.1 - p0.1);
+        Ok((p0.0 as f64 + t * (p1.0 - p0.0) as f64).round() as u16)
+    }
+
+    /// Returns the magnification at the tele end of the table.
+    pub fn max_magnification(&self) -> f64 {
+        self.points[self.points.len() - 1].1 / self.points[0].1
+    }
+
+    pub fn fov(&self, position: u16) -> FieldOfView {
+        let half_width = (self.wide_hfov / 2.0).to_radians().tan() / self.magnification(position);
+        let half_height = half_width / self.aspect_ratio;
+
+        FieldOfView {
+            horizontal: 2.0 * half_width.atan().to_degrees(),
+            vertical: 2.0 * half_height.atan().to_degrees(),
+        }
+    }
+
+    /// Returns the raw zoom position that gives a horizontal field of view in degrees.
+    pub fn position_for_fov(&self, horizontal: f64) -> Result<u16> {
+        if !(horizontal > 0.0 && horizontal < 180.0) {
+            return Err(Error::OutOfRange);
+        }
+
+        let wide = (self.wide_hfov / 2.0).to_radians().tan();
+        let narrow = (horizontal / 2.0).to_radians().tan();
+        self.position(wide / narrow)
+    }
+}
+
+impl<'a> Zoom<'a> {
+    pub fn get_magnification(&mut self, table: &ZoomTable) -> Result<f64> {
+        self.get().map(|pos| table.magnification(pos))
+    }
+
+    pub fn set_magnification(&mut self, magnification: f64, table: &ZoomTable) -> Result<()> {
+        let pos = table.position(magnification)?;
+        self.set(pos)
+    }
+
+    pub fn get_fov(&mut self, table: &ZoomTable) -> Result<FieldOfView> {
+        self.get().map(|pos| table.fov(pos))
+    }
+
+    /// Zooms to a horizontal field of view in degrees.
+    pub fn set_fov(&mut self, horizontal: f64, table: &ZoomTable) -> Result<()> {
+        let pos = table.position_for_fov(horizontal)?;
+        self.set(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::testing::assert_close;
+    use crate::Camera;
+
+    fn table() -> ZoomTable {
+        ZoomTable::new(
+            vec![(0x0000, 1.0), (0x2000, 4.0), (0x4000, 10.0)],
+            60.0,
+            16.0 / 9.0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_magnification() {
+        let table = table();
+        assert_close(table.magnification(0x0000), 1.0);
+        assert_close(table.magnification(0x1000), 2.5);
+        assert_close(table.magnification(0x3000), 7.0);
+        assert_close(table.magnification(0x5000), 10.0);
+    }
+
+    #[test]
+    fn test_position() {
+        let table = table();
+        assert_eq!(table.position(1.0).unwrap(), 0x0000);
+        assert_eq!(table.position(2.5).unwrap(), 0x1000);
+        assert_eq!(table.position(10.0).unwrap(), 0x4000);
+        assert!(table.position(0.5).is_err());
+        assert!(table.position(10.5).is_err());
+    }
+
+    #[test]
+    fn test_fov() {
+        let table = table();
+        let wide = table.fov(0x0000);
+        assert_close(wide.horizontal, 60.0);
+        assert!(wide.vertical < 60.0);
+
+        let tele = table.fov(0x4000);
+        assert!(tele.horizontal < 7.0);
+        assert_eq!(table.position_for_fov(tele.horizontal).unwrap(), 0x4000);
+        assert!(table.position_for_fov(90.0).is_err());
+    }
+
+    #[test]
+    fn test_invalid_table() {
+        for points in [
+            vec![(0x0000, 1.0)],
+            vec![(0x0000, 1.0), (0x0000, 2.0)],
+            vec![(0x0000, 2.0), (0x1000, 1.0)],
+        ] {
+            assert!(ZoomTable::new(points, 60.0, 1.5).is_err());
+        }
+    }
+
+    #[test]
+    fn test_set_magnification() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x01, 0x04, 0x47, 0x01, 0x00, 0x00, 0x00, 0xff])
+            .reply(&[0x90, 0x41, 0xff])
+            .reply(&[0x90, 0x51, 0xff]);
+
+        let mut camera = Camera::with_transport(mock);
+        camera.zoom().set_magnification(2.5, &table()).unwrap();
+    }
+
+    #[test]
+    fn test_model_table() {
+        let table = ZoomTable::for_model(0x040e).unwrap();
+        assert_close(table.magnification(0x4000), 18.0);
+        assert_close(table.fov(0x0000).horizontal, 48.0);
+        assert_eq!(ZoomTable::for_model(0x0000), None);
+    }
+}
@@ -1,25 +1,113 @@
 use std::error;
 use std::fmt;
 use std::io;
+use std::net::ToSocketAddrs;
 use std::path::Path;
 use std::result;
+use std::time::Duration;
 
+mod action;
+mod aim;
+mod calibration;
+mod capture;
 mod commands;
+mod cue;
+mod discovery;
+mod dissect;
+mod gateway;
 mod interface;
+mod ip;
+mod lens;
+#[cfg(any(test, feature = "sim"))]
+#[cfg_attr(not(feature = "sim"), allow(dead_code, unused_imports))]
+mod mock;
+mod models;
+mod monitor;
+mod motion;
 mod packet;
+mod parse;
+mod protocol;
+mod replay;
+mod scan;
+mod script;
+#[cfg(any(test, feature = "sim"))]
+#[cfg_attr(not(feature = "sim"), allow(dead_code, unused_imports))]
+mod sim;
+mod status;
+mod tcp;
+#[cfg(test)]
+mod testing;
+mod tour;
+mod transport;
+mod wait;
 
-use commands::{PanTilt, Presets, Zoom};
+use commands::{Focus, PanTilt, Power, Presets, Zoom};
 use interface::Interface;
+use ip::LinkError;
 
+pub use action::Action;
+pub use aim::{Point, Rect};
+pub use calibration::{Calibration, Degrees};
+pub use capture::{open_capture, read_capture, Flow, Record, Recorder};
 pub use commands::PanTiltValue;
-pub use packet::ErrorKind;
+pub use cue::{open_cue_list, read_cue_list, Cue, CueRunner, CueStep};
+pub use discovery::{discover, discover_at, Candidate, DISCOVERY_PORT};
+pub use dissect::{dissect, hex, parse_hex, Description, PacketKind};
+pub use gateway::{BusId, ForwardError, Gateway};
+pub use interface::DEFAULT_BAUD_RATE;
+pub use ip::VISCA_IP_PORT;
+pub use lens::{FieldOfView, ZoomTable};
+#[cfg(feature = "sim")]
+pub use mock::{Expectation, MockTransport};
+pub use models::Model;
+pub use monitor::{Event, Monitor, Readings};
+pub use motion::{Easing, Keyframe, SpeedTable, Timeline};
+pub use packet::{Category, ErrorKind, Message, Reply, Request};
+pub use protocol::{Command, Direction, Inquiry, InquiryReply, Version};
+pub use replay::ReplayTransport;
+pub use scan::{scan, FoundCamera, ScanResult, SCAN_BAUD_RATES};
+pub use script::{Script, ScriptRunner, Statement};
+#[cfg(all(feature = "sim", target_os = "linux"))]
+pub use sim::VirtualSerialPort;
+#[cfg(feature = "sim")]
+pub use sim::{DiscoveryResponder, Fault, FaultSchedule, SimTransport, Simulator};
+pub use status::{
+    CameraControlStatus, CameraStatus, EnlargementStatus, ExposureMode, LensStatus, OtherStatus,
+    WhiteBalance,
+};
+pub use tcp::{DEFAULT_CONNECT_TIMEOUT, VISCA_TCP_PORT};
+pub use tour::{Shot, Tour, TourOrder, TourStop};
+pub use transport::Transport;
+pub use wait::{Settled, POLL_INTERVAL, SETTLE_TIME};
 
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
     Camera(ErrorKind),
     InvalidReply,
+    InvalidRequest,
+    InvalidAddress,
+    InvalidPayload,
+    InvalidCapture(usize),
     ReadBufferFull,
+    /// The camera never replied to a request, even after it was sent again.
+    NoReply,
+    /// The camera didn't reach the expected state in time.
+    Timeout,
+    /// A position lies outside the range the camera can reach.
+    OutOfRange,
+    /// A calibration or lookup table is inconsistent.
+    InvalidCalibration,
+    /// Keyframes are missing or out of order.
+    InvalidTimeline,
+    /// A cue list file couldn't be parsed, at the given line.
+    InvalidCueList(usize),
+    UnknownCue(String),
+    UnknownCamera(String),
+    /// A script was run before any camera was added to run it on.
+    NoCamera,
+    /// A script couldn't be parsed, at the given line.
+    InvalidScript(usize),
 }
 
 impl fmt::Display for Error {
@@ -28,7 +116,21 @@ impl fmt::Display for Error {
             Error::Io(err) => err.fmt(f),
             Error::Camera(kind) => write!(f, "{}", kind.as_str()),
             Error::InvalidReply => write!(f, "invalid reply"),
+            Error::InvalidRequest => write!(f, "invalid request"),
+            Error::InvalidAddress => write!(f, "camera address must be between 1 and 7"),
+            Error::InvalidPayload => write!(f, "invalid request payload"),
+            Error::InvalidCapture(line) => write!(f, "invalid capture file at line {}", line),
             Error::ReadBufferFull => write!(f, "read buffer is full"),
+            Error::NoReply => write!(f, "camera did not reply"),
+            Error::Timeout => write!(f, "timed out waiting for the camera"),
+            Error::OutOfRange => write!(f, "position is out of range"),
+            Error::InvalidCalibration => write!(f, "invalid calibration"),
+            Error::InvalidTimeline => write!(f, "invalid keyframe timeline"),
+            Error::InvalidCueList(line) => write!(f, "invalid cue list at line {}", line),
+            Error::UnknownCue(number) => write!(f, "no cue numbered {}", number),
+            Error::UnknownCamera(name) => write!(f, "no camera named {}", name),
+            Error::NoCamera => write!(f, "no camera to run the script on"),
+            Error::InvalidScript(line) => write!(f, "invalid script at line {}", line),
         }
     }
 }
@@ -44,7 +146,11 @@ impl error::Error for Error {
 
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Self {
-        Error::Io(error)
+        match error.get_ref().and_then(|err| err.downcast_ref()) {
+            Some(LinkError::NoReply) => Error::NoReply,
+            Some(LinkError::MessageRejected) => Error::InvalidRequest,
+            None => Error::Io(error),
+        }
     }
 }
 
@@ -52,6 +158,7 @@ pub type Result<T> = result::Result<T, Error>;
 
 pub struct Camera {
     iface: Interface,
+    address: u8,
 }
 
 impl Camera {
@@ -60,19 +167,131 @@ impl Camera {
         Ok(Camera::new(iface))
     }
 
+    pub fn open_with_baud_rate<P: AsRef<Path>>(path: P, baud_rate: u32) -> Result<Self> {
+        let iface = Interface::open_with_baud_rate(path, baud_rate)?;
+        Ok(Camera::new(iface))
+    }
+
+    /// Connects to a camera that speaks VISCA-over-IP, usually on port 52381.
+    pub fn connect_udp<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let iface = Interface::connect_udp(addr)?;
+        Ok(Camera::new(iface))
+    }
+
+    /// Connects to a camera that accepts raw VISCA over TCP, usually on port 5678.
+    ///
+    /// The connection is re-established automatically if the camera drops it.
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Camera::connect_tcp_with_timeout(addr, DEFAULT_CONNECT_TIMEOUT)
+    }
+
+    pub fn connect_tcp_with_timeout<A: ToSocketAddrs>(addr: A, timeout: Duration) -> Result<Self> {
+        let iface = Interface::connect_tcp(addr, timeout)?;
+        Ok(Camera::new(iface))
+    }
+
+    /// Creates a camera that communicates through a custom transport, such as a
+    /// `ReplayTransport` in tests.
+    pub fn with_transport<T: Transport + 'static>(transport: T) -> Self {
+        Camera::new(Interface::new(Box::new(transport)))
+    }
+
     fn new(iface: Interface) -> Self {
-        Camera { iface }
+        Camera { iface, address: 1 }
     }
 
-    pub fn pan_tilt(&mut self) -> PanTilt {
-        PanTilt::new(&mut self.iface)
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Sets the address of the camera on a daisy-chained serial bus, between 1 and 7.
+    pub fn set_address(&mut self, address: u8) -> Result<()> {
+        if !(1..=7).contains(&address) {
+            return Err(Error::InvalidAddress);
+        }
+        self.address = address;
+        Ok(())
     }
 
-    pub fn presets(&mut self) -> Presets {
-        Presets::new(&mut self.iface)
+    /// Records every packet exchanged with the camera, or stops recording if `None`.
+    pub fn set_recorder(&mut self, recorder: Option<Recorder>) {
+        self.iface.set_recorder(recorder);
     }
 
-    pub fn zoom(&mut self) -> Zoom {
-        Zoom::new(&mut self.iface)
+    pub fn is_recording(&self) -> bool {
+        self.iface.is_recording()
+    }
+
+    /// Returns the write error that stopped the last recording, if any.
+    pub fn recording_error(&self) -> Option<&Error> {
+        self.iface.recording_error()
+    }
+
+    pub fn pan_tilt(&mut self) -> PanTilt<'_> {
+        PanTilt::new(&mut self.iface, self.address)
+    }
+
+    pub fn presets(&mut self) -> Presets<'_> {
+        Presets::new(&mut self.iface, self.address)
+    }
+
+    pub fn zoom(&mut self) -> Zoom<'_> {
+        Zoom::new(&mut self.iface, self.address)
+    }
+
+    pub fn focus(&mut self) -> Focus<'_> {
+        Focus::new(&mut self.iface, self.address)
+    }
+
+    pub fn power(&mut self) -> Power<'_> {
+        Power::new(&mut self.iface, self.address)
+    }
+
+    pub fn version(&mut self) -> Result<Version> {
+        commands::version(&mut self.iface, self.address)
+    }
+
+    /// Reads the lens, exposure and other settings with four block inquiries, which is
+    /// much quicker than asking for each setting separately.
+    pub fn status(&mut self) -> Result<CameraStatus> {
+        commands::status(&mut self.iface, self.address)
+    }
+
+    /// Sends a complete, already-encoded request and returns the camera's final reply.
+    pub fn send_request(&mut self, req: &Request) -> Result<Reply> {
+        self.iface.send_request_with_reply(req)
+    }
+
+    /// Sends a complete request, passing each reply to `each` as it arrives, including the
+    /// ACK and any error. Returns the final completion.
+    pub fn send_request_with_replies<F>(&mut self, req: &Request, each: F) -> Result<Reply>
+    where
+        F: FnMut(&Reply),
+    {
+        self.iface.send_request_with_replies(req, each)
+    }
+
+    /// Sends an arbitrary command to the camera and waits for it to complete.
+    pub fn send_command(&mut self, category: Category, payload: &[u8]) -> Result<()> {
+        let req = Request::new()
+            .address(self.address)?
+            .command()
+            .category(category)
+            .payload(payload)?;
+
+        self.iface.send_request_with_reply(&req).map(|_| ())
+    }
+
+    /// Sends an arbitrary inquiry to the camera and returns the payload of its reply.
+    pub fn inquire(&mut self, category: Category, payload: &[u8]) -> Result<Vec<u8>> {
+        let req = Request::new()
+            .address(self.address)?
+            .inquiry()
+            .category(category)
+            .payload(payload)?;
+
+        self.iface
+            .send_request_with_reply(&req)
+            .map(|reply| reply.payload().to_vec())
     }
 }
@@ -0,0 +1,102 @@
+use crate::dissect::{dissect, hex};
+use crate::transport::Transport;
+use std::collections::VecDeque;
+use std::io;
+use std::thread;
+
+/// A transport for unit tests that checks each request against a scripted list of
+/// expectations and answers it with canned replies.
+///
+/// ```
+/// use visca::{Camera, MockTransport};
+///
+/// let mut mock = MockTransport::new();
+/// mock.expect(&[0x81, 0x09, 0x04, 0x47, 0xff])
+///     .reply(&[0x90, 0x50, 0x01, 0x02, 0x03, 0x04, 0xff]);
+///
+/// let mut camera = Camera::with_transport(mock);
+/// assert_eq!(camera.zoom().get().unwrap(), 0x1234);
+/// ```
+///
+/// Sending an unexpected request panics, and so does dropping the transport while some
+/// expectations are still unmet.
+#[derive(Default)]
+pub struct MockTransport {
+    expectations: VecDeque<Expectation>,
+    replies: VecDeque<Vec<u8>>,
+}
+
+pub struct Expectation {
+    request: Vec<u8>,
+    replies: Vec<Vec<u8>>,
+}
+
+impl Expectation {
+    /// Queues a reply to be returned once the expected request has been sent.
+    pub fn reply(&mut self, bytes: &[u8]) -> &mut Self {
+        self.replies.push(bytes.to_vec());
+        self
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport::default()
+    }
+
+    /// Adds an expectation that the next request will be exactly `bytes`.
+    pub fn expect(&mut self, bytes: &[u8]) -> &mut Expectation {
+        self.expectations.push_back(Expectation {
+            request: bytes.to_vec(),
+            replies: Vec::new(),
+        });
+        self.expectations.back_mut().unwrap()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        let expectation = match self.expectations.pop_front() {
+            Some(expectation) => expectation,
+            None => panic!("unexpected request {} ({})", hex(packet), dissect(packet)),
+        };
+
+        assert!(
+            packet == &expectation.request[..],
+            "sent {} ({}) but expected {} ({})",
+            hex(packet),
+            dissect(packet),
+            hex(&expectation.request),
+            dissect(&expectation.request)
+        );
+
+        self.replies.extend(expectation.replies);
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.replies.pop_front() {
+            Some(reply) => {
+                let len = reply.len().min(buf.len());
+                buf[..len].copy_from_slice(&reply[..len]);
+                Ok(len)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no reply was scripted",
+            )),
+        }
+    }
+}
+
+impl Drop for MockTransport {
+    fn drop(&mut self) {
+        if !thread::panicking() && !self.expectations.is_empty() {
+            panic!(
+                "{} expected requests were never sent, starting with {}",
+                self.expectations.len(),
+                hex(&self.expectations[0].request)
+            );
+        }
+    }
+}
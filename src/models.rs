@@ -0,0 +1,197 @@
+//! Specifications of camera models, looked up by the model id a camera reports in its
+//! version inquiry.
+//!
+//! Ranges and top speeds are taken from the models' technical manuals. The manuals only
+//! give the top pan and tilt speeds, so the speeds in between are taken to step up evenly,
+//! and variable lens speeds to each add an eighth of the lens's top speed.
+
+use crate::calibration::Calibration;
+use crate::commands::PanTiltValue;
+use crate::lens::ZoomTable;
+use crate::motion::SpeedTable;
+use crate::protocol::Version;
+
+/// The vendor id Sony cameras report.
+const SONY: u16 = 0x0001;
+
+/// What a camera model can reach and how fast it gets there.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Model {
+    pub name: &'static str,
+    /// The model ids the camera reports, such as one for each video standard.
+    pub model_ids: &'static [u16],
+    pub calibration: Calibration,
+    /// `(position, magnification)` points along the zoom range, from wide to tele.
+    pub zoom_points: &'static [(u16, f64)],
+    /// The horizontal field of view at the wide end, in degrees.
+    pub wide_hfov: f64,
+    pub aspect_ratio: f64,
+    /// The fastest pan speed, and how many degrees per second it turns at.
+    pub pan_speed_max: (u8, f64),
+    /// The fastest tilt speed, and how many degrees per second it turns at.
+    pub tilt_speed_max: (u8, f64),
+    /// How many seconds the lens takes to zoom from wide to tele at its top speed.
+    pub zoom_travel: f64,
+    /// The focus positions at infinity and at the closest distance.
+    pub focus_range: (u16, u16),
+    /// How many seconds the focus takes to cross its range at its top speed.
+    pub focus_travel: f64,
+}
+
+impl Model {
+    pub const EVI_D30: Model = Model {
+        name: "EVI-D30",
+        model_ids: &[0x0402],
+        calibration: Calibration {
+            pan_units_per_degree: 8.8,
+            tilt_units_per_degree: 12.0,
+            center: PanTiltValue { pan: 0, tilt: 0 },
+            pan_min: -100.0,
+            pan_max: 100.0,
+            tilt_min: -25.0,
+            tilt_max: 25.0,
+        },
+        // The manual only gives the ends of the zoom range.
+        zoom_points: &[(0x0000, 1.0), (0x03ff, 12.0)],
+        wide_hfov: 48.8,
+        aspect_ratio: 4.0 / 3.0,
+        pan_speed_max: (0x18, 80.0),
+        tilt_speed_max: (0x14, 50.0),
+        zoom_travel: 2.5,
+        focus_range: (0x1000, 0x9fff),
+        focus_travel: 2.0,
+    };
+
+    pub const EVI_D70: Model = Model {
+        name: "EVI-D70",
+        model_ids: &[0x040e, 0x040f],
+        calibration: Calibration {
+            pan_units_per_degree: 2267.0 / 170.0,
+            tilt_units_per_degree: 1200.0 / 90.0,
+            center: PanTiltValue { pan: 0, tilt: 0 },
+            pan_min: -170.0,
+            pan_max: 170.0,
+            tilt_min: -30.0,
+            tilt_max: 90.0,
+        },
+        zoom_points: &[
+            (0x0000, 1.0),
+            (0x1606, 2.0),
+            (0x2151, 3.0),
+            (0x2860, 4.0),
+            (0x2cb5, 5.0),
+            (0x3060, 6.0),
+            (0x32d3, 7.0),
+            (0x3545, 8.0),
+            (0x36e8, 9.0),
+            (0x384d, 10.0),
+            (0x3986, 11.0),
+            (0x3a8f, 12.0),
+            (0x3b6e, 13.0),
+            (0x3c28, 14.0),
+            (0x3cc0, 15.0),
+            (0x3d41, 16.0),
+            (0x3dac, 17.0),
+            (0x4000, 18.0),
+        ],
+        wide_hfov: 48.0,
+        aspect_ratio: 4.0 / 3.0,
+        pan_speed_max: (0x18, 100.0),
+        tilt_speed_max: (0x17, 90.0),
+        zoom_travel: 3.0,
+        focus_range: (0x1000, 0xc000),
+        focus_travel: 2.0,
+    };
+
+    /// Every model with known specifications.
+    pub const ALL: &'static [Model] = &[Model::EVI_D30, Model::EVI_D70];
+
+    /// Returns the model that reports `model_id`.
+    pub fn for_id(model_id: u16) -> Option<&'static Model> {
+        Model::ALL
+            .iter()
+            .find(|model| model.model_ids.contains(&model_id))
+    }
+
+    /// Returns the model a camera is, from its reply to a version inquiry.
+    pub fn for_version(version: &Version) -> Option<&'static Model> {
+        if version.vendor_id != SONY {
+            return None;
+        }
+        Model::for_id(version.model_id)
+    }
+
+    pub fn zoom_table(&self) -> ZoomTable {
+        ZoomTable::new(self.zoom_points.to_vec(), self.wide_hfov, self.aspect_ratio)
+            .expect("model zoom points are sorted")
+    }
+
+    pub fn speed_table(&self) -> SpeedTable {
+        let cal = &self.calibration;
+        let steps = |(max, degrees_per_sec): (u8, f64), units_per_degree: f64| {
+            let top = degrees_per_sec * units_per_degree;
+            (1..=max).map(|s| top * s as f64 / max as f64).collect()
+        };
+        let lens = |range: f64, travel: f64| {
+            (0..8)
+                .map(|s| range / travel * (s + 1) as f64 / 8.0)
+                .collect()
+        };
+
+        let zoom_range = self.zoom_points[self.zoom_points.len() - 1].0 - self.zoom_points[0].0;
+        let focus_range = self.focus_range.1 - self.focus_range.0;
+
+        SpeedTable::new(
+            steps(self.pan_speed_max, cal.pan_units_per_degree),
+            steps(self.tilt_speed_max, cal.tilt_units_per_degree),
+            lens(zoom_range as f64, self.zoom_travel),
+            lens(focus_range as f64, self.focus_travel),
+        )
+        .expect("model speeds are positive")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_version() {
+        let version = Version {
+            vendor_id: SONY,
+            model_id: 0x040f,
+            rom_version: 0x0100,
+            max_socket: 2,
+        };
+        assert_eq!(Model::for_version(&version), Some(&Model::EVI_D70));
+
+        let other = Version {
+            vendor_id: 0x0020,
+            ..version
+        };
+        assert_eq!(Model::for_version(&other), None);
+        assert_eq!(Model::for_id(0x0000), None);
+    }
+
+    #[test]
+    fn test_tables() {
+        for model in Model::ALL {
+            let table = model.zoom_table();
+            let (_, tele) = model.zoom_points[model.zoom_points.len() - 1];
+            assert_eq!(table.magnification(0xffff), tele, "{}", model.name);
+
+            let speeds = model.speed_table();
+            assert_eq!(speeds.pan().len(), model.pan_speed_max.0 as usize);
+            assert_eq!(speeds.tilt().len(), model.tilt_speed_max.0 as usize);
+            assert_eq!(speeds.zoom().len(), 8);
+        }
+    }
+
+    #[test]
+    fn test_top_speeds() {
+        let speeds = Model::EVI_D70.speed_table();
+        let top =
+            speeds.pan()[speeds.pan().len() - 1] / Model::EVI_D70.calibration.pan_units_per_degree;
+        assert!((top - 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,267 @@
+use crate::commands::PanTiltValue;
+use crate::{Camera, Result};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A change in the camera's position noticed by a `Monitor`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    PanTilt(PanTiltValue),
+    Zoom(u16),
+    Focus(u16),
+    /// The camera stopped answering inquiries, for the given reason. This is sent once,
+    /// and readings resume being reported when the camera answers again.
+    Error(String),
+}
+
+/// The latest values read by a `Monitor`. Each is `None` until the camera first answers.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Readings {
+    pub pan_tilt: Option<PanTiltValue>,
+    pub zoom: Option<u16>,
+    pub focus: Option<u16>,
+}
+
+struct Shared {
+    camera: Mutex<Camera>,
+    readings: Mutex<Readings>,
+    /// Why the last round of inquiries failed, until one succeeds.
+    error: Mutex<Option<String>>,
+    subscribers: Mutex<Vec<Sender<Event>>>,
+}
+
+/// Polls a camera's position in the background and reports when it changes.
+///
+/// The monitor owns the camera while it runs. Commands can still be sent through
+/// `camera()`, and are interleaved with the inquiries the monitor makes.
+pub struct Monitor {
+    shared: Arc<Shared>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Monitor {
+    /// Starts polling `camera` every `interval`.
+    pub fn start(camera: Camera, interval: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            camera: Mutex::new(camera),
+            readings: Mutex::new(Readings::default()),
+            error: Mutex::new(None),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        let (stop, stopped) = mpsc::channel();
+
+        let poller = shared.clone();
+        let thread = thread::spawn(move || poller.poll(interval, stopped));
+
+        Monitor {
+            shared,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Gives access to the camera between two inquiries. Polling waits for as long as the
+    /// camera is held, so don't hold on to it.
+    pub fn camera(&self) -> MutexGuard<'_, Camera> {
+        self.shared.camera.lock().unwrap()
+    }
+
+    pub fn readings(&self) -> Readings {
+        *self.shared.readings.lock().unwrap()
+    }
+
+    /// Returns why the camera isn't answering, or `None` if the last inquiries succeeded.
+    /// The readings are then the last ones the camera gave.
+    pub fn error(&self) -> Option<String> {
+        self.shared.error.lock().unwrap().clone()
+    }
+
+    /// Returns a channel that receives an event whenever a reading changes or the camera
+    /// stops answering. The readings made so far are sent straight away.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (tx, rx) = mpsc::channel();
+        let readings = self.shared.readings.lock().unwrap();
+
+        let current = [
+            readings.pan_tilt.map(Event::PanTilt),
+            readings.zoom.map(Event::Zoom),
+            readings.focus.map(Event::Focus),
+            self.error().map(Event::Error),
+        ];
+        for event in current.iter().flatten() {
+            let _ = tx.send(event.clone());
+        }
+
+        self.shared.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Stops polling and hands the camera back.
+    pub fn stop(mut self) -> Camera {
+        self.shutdown();
+
+        let shared = self.shared.clone();
+        drop(self);
+
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => shared.camera.into_inner().unwrap(),
+            Err(_) => unreachable!("the polling thread has exited"),
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("monitor thread panicked");
+        }
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Shared {
+    fn poll(&self, interval: Duration, stopped: Receiver<()>) {
+        loop {
+            let started = Instant::now();
+
+            // Each inquiry takes the camera separately, so that commands can slip in between.
+            let pan_tilt = self.camera.lock().unwrap().pan_tilt().get();
+            let zoom = self.camera.lock().unwrap().zoom().get();
+            let focus = self.camera.lock().unwrap().focus().get();
+            self.update(pan_tilt, zoom, focus);
+
+            let wait = interval.checked_sub(started.elapsed()).unwrap_or_default();
+            match stopped.recv_timeout(wait) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+        }
+    }
+
+    /// Caches the values that were read successfully and publishes those that changed,
+    /// or the first error once every inquiry fails.
+    fn update(&self, pan_tilt: Result<PanTiltValue>, zoom: Result<u16>, focus: Result<u16>) {
+        let mut events = Vec::new();
+        let mut readings = self.readings.lock().unwrap();
+        let mut error = self.error.lock().unwrap();
+
+        match (&pan_tilt, &zoom, &focus) {
+            (Err(err), Err(_), Err(_)) => {
+                if error.is_none() {
+                    *error = Some(err.to_string());
+                    events.push(Event::Error(err.to_string()));
+                }
+            }
+            _ => *error = None,
+        }
+        let (pan_tilt, zoom, focus) = (pan_tilt.ok(), zoom.ok(), focus.ok());
+
+        if pan_tilt.is_some() && pan_tilt != readings.pan_tilt {
+            readings.pan_tilt = pan_tilt;
+            events.extend(pan_tilt.map(Event::PanTilt));
+        }
+        if zoom.is_some() && zoom != readings.zoom {
+            readings.zoom = zoom;
+            events.extend(zoom.map(Event::Zoom));
+        }
+        if focus.is_some() && focus != readings.focus {
+            readings.focus = focus;
+            events.extend(focus.map(Event::Focus));
+        }
+
+        // Subscribers that have hung up are forgotten.
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| events.iter().all(|event| tx.send(event.clone()).is_ok()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{Fault, FaultSchedule, Simulator};
+    use crate::testing::{camera, camera_with};
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    fn monitor() -> Monitor {
+        Monitor::start(camera(), INTERVAL)
+    }
+
+    #[test]
+    fn test_first_readings() {
+        let monitor = monitor();
+        let events = monitor.subscribe();
+
+        let mut seen = Vec::new();
+        while seen.len() < 3 {
+            seen.push(events.recv_timeout(Duration::from_secs(1)).unwrap());
+        }
+
+        assert_eq!(
+            seen,
+            vec![
+                Event::PanTilt(PanTiltValue { pan: 0, tilt: 0 }),
+                Event::Zoom(0),
+                Event::Focus(0x1000),
+            ]
+        );
+        assert_eq!(monitor.readings().zoom, Some(0));
+    }
+
+    #[test]
+    fn test_zoom_changes() {
+        let monitor = monitor();
+        let events = monitor.subscribe();
+
+        monitor.camera().zoom().set(0x800).unwrap();
+
+        // The zoom is reported on its way to the target, and then at the target.
+        loop {
+            match events.recv_timeout(Duration::from_secs(1)).unwrap() {
+                Event::Zoom(0x800) => break,
+                Event::Zoom(zoom) => assert!(zoom < 0x800),
+                _ => {}
+            }
+        }
+        assert_eq!(monitor.readings().zoom, Some(0x800));
+    }
+
+    #[test]
+    fn test_stop_returns_camera() {
+        let monitor = monitor();
+        let mut camera = monitor.stop();
+        assert!(camera.power().get().unwrap());
+    }
+
+    #[test]
+    fn test_camera_stops_answering() {
+        // The first round of inquiries is answered, and nothing after it.
+        let mut sim = Simulator::new();
+        sim.set_faults(FaultSchedule::new().from(3, Fault::Silence));
+        let monitor = Monitor::start(camera_with(sim), INTERVAL);
+        let events = monitor.subscribe();
+
+        loop {
+            if let Event::Error(_) = events.recv_timeout(Duration::from_secs(1)).unwrap() {
+                break;
+            }
+        }
+        assert!(monitor.error().is_some());
+        assert_eq!(monitor.readings().zoom, Some(0));
+
+        // The error is reported once, and to later subscribers too.
+        assert!(events.recv_timeout(INTERVAL * 5).is_err());
+        let late = monitor.subscribe();
+        let replayed: Vec<_> = late.try_iter().collect();
+        assert!(matches!(replayed.last(), Some(Event::Error(_))));
+    }
+}
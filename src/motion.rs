@@ -0,0 +1,795 @@
+//! Smooth, timed camera moves through a series of keyframes.
+//!
+//! Rather than sending each keyframe as an absolute move, the camera is driven with
+//! variable-speed commands that are updated several times a second. Each update reads
+//! the camera's position back and corrects the speeds, so the move keeps to its schedule
+//! even though drive speeds only come in coarse steps.
+//!
+//! A single move to one position is simpler: each axis is given the speed that gets it
+//! there in the same time as the others, and the camera does the rest.
+
+use crate::commands::{execute, PanTiltValue};
+use crate::models::Model;
+use crate::protocol::{Command, Direction};
+use crate::{Camera, Error, Result};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the camera's position is read and its speeds updated.
+const TICK: Duration = Duration::from_millis(100);
+
+/// How strongly the speeds are corrected for the distance the camera is off course, per
+/// second. Higher values catch up faster but overshoot more.
+const GAIN: f64 = 2.0;
+
+/// How fast a model's axes travel at each of their speeds, in position units per second.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpeedTable {
+    pan: Vec<f64>,
+    tilt: Vec<f64>,
+    zoom: Vec<f64>,
+    focus: Vec<f64>,
+}
+
+impl SpeedTable {
+    /// Creates a table from the pan and tilt speeds from 0x01 upwards, and the variable
+    /// zoom and focus speeds from 0 to 7. Each list must be strictly increasing and made
+    /// of positive speeds.
+    pub fn new(pan: Vec<f64>, tilt: Vec<f64>, zoom: Vec<f64>, focus: Vec<f64>) -> Result<Self> {
+        let valid = |speeds: &[f64]| {
+            !speeds.is_empty()
+                && speeds.len() <= u8::MAX as usize
+                && speeds[0] > 0.0
+                && speeds.iter().all(|speed| speed.is_finite())
+                && speeds.windows(2).all(|pair| pair[0] < pair[1])
+        };
+
+        if !(valid(&pan) && valid(&tilt) && valid(&zoom) && valid(&focus))
+            || zoom.len() != 8
+            || focus.len() != 8
+        {
+            return Err(Error::InvalidCalibration);
+        }
+
+        Ok(SpeedTable {
+            pan,
+            tilt,
+            zoom,
+            focus,
+        })
+    }
+
+    /// Pan speeds from 0x01 upwards.
+    pub fn pan(&self) -> &[f64] {
+        &self.pan
+    }
+
+    /// Tilt speeds from 0x01 upwards.
+    pub fn tilt(&self) -> &[f64] {
+        &self.tilt
+    }
+
+    /// Variable zoom speeds from 0 to 7.
+    pub fn zoom(&self) -> &[f64] {
+        &self.zoom
+    }
+
+    /// Variable focus speeds from 0 to 7.
+    pub fn focus(&self) -> &[f64] {
+        &self.focus
+    }
+
+    /// Returns the speed table of the model that reports `model_id`, if it's known.
+    pub fn for_model(model_id: u16) -> Option<Self> {
+        Model::for_id(model_id).map(Model::speed_table)
+    }
+
+    /// Returns the index of the speed closest to `rate`, or `None` if `rate` is closer to
+    /// standing still than to the slowest speed.
+    fn nearest(speeds: &[f64], rate: f64) -> Option<usize> {
+        let rate = rate.abs();
+        if speeds.is_empty() || rate < speeds[0] / 2.0 {
+            return None;
+        }
+
+        speeds
+            .iter()
+            .enumerate()
+            .min_by(|a, b| (a.1 - rate).abs().total_cmp(&(b.1 - rate).abs()))
+            .map(|(i, _)| i)
+    }
+
+    /// Returns the shortest time an axis can cover `distance` in at its top speed.
+    fn shortest(speeds: &[f64], distance: f64) -> f64 {
+        match speeds.last() {
+            Some(&top) if top > 0.0 => distance.abs() / top,
+            _ => 0.0,
+        }
+    }
+}
+
+/// How the camera speeds up and slows down between two keyframes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Starts slowly and arrives at full speed.
+    EaseIn,
+    /// Starts at full speed and slows down on arrival.
+    EaseOut,
+    /// Starts and arrives slowly.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the fraction of time elapsed to the fraction of the distance covered.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Where the camera should be at a point in time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    /// When the camera should get there, measured from the start of the move.
+    pub at: Duration,
+    pub pan_tilt: PanTiltValue,
+    pub zoom: u16,
+    /// The focus position, or `None` to leave the focus alone.
+    pub focus: Option<u16>,
+    /// How the camera moves on its way to this keyframe from the one before.
+    pub easing: Easing,
+}
+
+/// A series of keyframes for the camera to move through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeline {
+    keyframes: Vec<Keyframe>,
+}
+
+/// A position on a timeline, kept fractional between keyframes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Sample {
+    pub pan: f64,
+    pub tilt: f64,
+    pub zoom: f64,
+    pub focus: Option<f64>,
+}
+
+impl Timeline {
+    /// Creates a timeline, failing with `Error::InvalidTimeline` unless there is at least
+    /// one keyframe and their times strictly increase.
+    pub fn new(keyframes: Vec<Keyframe>) -> Result<Self> {
+        let ordered = keyframes.windows(2).all(|pair| pair[0].at < pair[1].at);
+        if keyframes.is_empty() || !ordered {
+            return Err(Error::InvalidTimeline);
+        }
+
+        Ok(Timeline { keyframes })
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Returns how long the move takes.
+    pub fn duration(&self) -> Duration {
+        self.keyframes[self.keyframes.len() - 1].at
+    }
+
+    /// Returns the timeline with a keyframe at time zero for wherever the camera starts,
+    /// unless it already has one.
+    fn starting_from(&self, start: Keyframe) -> Timeline {
+        let mut keyframes = self.keyframes.clone();
+        if keyframes[0].at > Duration::from_secs(0) {
+            keyframes.insert(0, start);
+        }
+        Timeline { keyframes }
+    }
+
+    pub(crate) fn sample(&self, t: f64) -> Sample {
+        let first = &self.keyframes[0];
+        let i = match self.keyframes.iter().position(|k| k.at.as_secs_f64() > t) {
+            Some(0) => return sample_at(first, first, 0.0),
+            Some(i) => i,
+            None => {
+                let last = &self.keyframes[self.keyframes.len() - 1];
+                return sample_at(last, last, 0.0);
+            }
+        };
+
+        let (from, to) = (&self.keyframes[i - 1], &self.keyframes[i]);
+        let span = (to.at - from.at).as_secs_f64();
+        let progress = to.easing.apply((t - from.at.as_secs_f64()) / span);
+        sample_at(from, to, progress)
+    }
+
+    /// Returns the rate of change of each axis at time `t`, in units per second.
+    fn velocity(&self, t: f64) -> Sample {
+        let dt = 0.01;
+        let (before, after) = (self.sample(t - dt), self.sample(t + dt));
+
+        Sample {
+            pan: (after.pan - before.pan) / (2.0 * dt),
+            tilt: (after.tilt - before.tilt) / (2.0 * dt),
+            zoom: (after.zoom - before.zoom) / (2.0 * dt),
+            focus: after
+                .focus
+                .zip(before.focus)
+                .map(|(a, b)| (a - b) / (2.0 * dt)),
+        }
+    }
+}
+
+fn sample_at(from: &Keyframe, to: &Keyframe, progress: f64) -> Sample {
+    let lerp = |a: f64, b: f64| a + (b - a) * progress;
+
+    Sample {
+        pan: lerp(from.pan_tilt.pan as f64, to.pan_tilt.pan as f64),
+        tilt: lerp(from.pan_tilt.tilt as f64, to.pan_tilt.tilt as f64),
+        zoom: lerp(from.zoom as f64, to.zoom as f64),
+        focus: from
+            .focus
+            .zip(to.focus)
+            .map(|(a, b)| lerp(a as f64, b as f64)),
+    }
+}
+
+/// The drive commands last sent to the camera, so that unchanged ones aren't repeated.
+#[derive(Default)]
+struct Drives {
+    pan_tilt: Option<Command>,
+    zoom: Option<Command>,
+    focus: Option<Command>,
+}
+
+impl Camera {
+    /// Moves the camera through the keyframes of `timeline`, starting from wherever it is
+    /// now, and returns once the last one has been reached.
+    ///
+    /// If a request fails along the way, the camera is told to stop all its axes before the
+    /// error is returned, so that it isn't left driving at its last speeds.
+    pub fn play(&mut self, timeline: &Timeline, speeds: &SpeedTable) -> Result<()> {
+        let result = self.follow(timeline, speeds);
+        if result.is_err() {
+            let _ = self.pan_tilt().stop();
+            let _ = self.zoom().stop();
+            let _ = self.focus().stop();
+        }
+        result
+    }
+
+    fn follow(&mut self, timeline: &Timeline, speeds: &SpeedTable) -> Result<()> {
+        let first = timeline.keyframes[0];
+        let start = Keyframe {
+            at: Duration::from_secs(0),
+            pan_tilt: self.pan_tilt().get()?,
+            zoom: self.zoom().get()?,
+            focus: match first.focus {
+                Some(_) => Some(self.focus().get()?),
+                None => None,
+            },
+            easing: Easing::Linear,
+        };
+
+        let timeline = timeline.starting_from(start);
+        let end = timeline.duration();
+        let mut drives = Drives::default();
+        let started = Instant::now();
+
+        while started.elapsed() < end {
+            let tick = Instant::now();
+            self.steer(
+                &timeline,
+                started.elapsed().as_secs_f64(),
+                speeds,
+                &mut drives,
+            )?;
+            thread::sleep(TICK.saturating_sub(tick.elapsed()));
+        }
+
+        // Whatever error is left is taken up by moving straight to the last keyframe.
+        let last = timeline.keyframes[timeline.keyframes.len() - 1];
+        self.pan_tilt().stop()?;
+        self.zoom().stop()?;
+        self.pan_tilt().set_absolute(last.pan_tilt)?;
+        self.zoom().set(last.zoom)?;
+        if drives.focus.is_some() {
+            self.focus().stop()?;
+        }
+        if let Some(focus) = last.focus {
+            self.focus().set(focus)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the camera's position and updates its speeds to keep to the timeline at `t`.
+    fn steer(
+        &mut self,
+        timeline: &Timeline,
+        t: f64,
+        speeds: &SpeedTable,
+        drives: &mut Drives,
+    ) -> Result<()> {
+        let target = timeline.sample(t);
+        let velocity = timeline.velocity(t);
+
+        let pan_tilt = self.pan_tilt().get()?;
+        let pan = velocity.pan + GAIN * (target.pan - pan_tilt.pan as f64);
+        let tilt = velocity.tilt + GAIN * (target.tilt - pan_tilt.tilt as f64);
+        self.drive(&mut drives.pan_tilt, pan_tilt_drive(pan, tilt, speeds))?;
+
+        let zoom = self.zoom().get()?;
+        let rate = velocity.zoom + GAIN * (target.zoom - zoom as f64);
+        let cmd = match SpeedTable::nearest(&speeds.zoom, rate) {
+            None => Command::ZoomStop,
+            Some(i) if rate > 0.0 => Command::ZoomTele(i as u8),
+            Some(i) => Command::ZoomWide(i as u8),
+        };
+        self.drive(&mut drives.zoom, cmd)?;
+
+        // Focus is left alone between keyframes without a focus position, but it mustn't
+        // keep driving at whatever speed the last segment with one left it at.
+        if let Some(target) = target.focus {
+            let focus = self.focus().get()?;
+            let rate = velocity.focus.unwrap_or(0.0) + GAIN * (target - focus as f64);
+            let cmd = match SpeedTable::nearest(&speeds.focus, rate) {
+                None => Command::FocusStop,
+                Some(i) if rate > 0.0 => Command::FocusNear(i as u8),
+                Some(i) => Command::FocusFar(i as u8),
+            };
+            self.drive(&mut drives.focus, cmd)?;
+        } else if drives.focus.is_some() {
+            self.drive(&mut drives.focus, Command::FocusStop)?;
+        }
+
+        Ok(())
+    }
+
+    fn drive(&mut self, last: &mut Option<Command>, cmd: Command) -> Result<()> {
+        if *last != Some(cmd) {
+            execute(&mut self.iface, self.address, cmd)?;
+            *last = Some(cmd);
+        }
+        Ok(())
+    }
+}
+
+fn pan_tilt_drive(pan: f64, tilt: f64, speeds: &SpeedTable) -> Command {
+    let pan_speed = SpeedTable::nearest(&speeds.pan, pan);
+    let tilt_speed = SpeedTable::nearest(&speeds.tilt, tilt);
+
+    let direction = match (pan_speed.map(|_| pan > 0.0), tilt_speed.map(|_| tilt > 0.0)) {
+        (None, None) => Direction::Stop,
+        (None, Some(true)) => Direction::Up,
+        (None, Some(false)) => Direction::Down,
+        (Some(false), None) => Direction::Left,
+        (Some(true), None) => Direction::Right,
+        (Some(false), Some(true)) => Direction::UpLeft,
+        (Some(true), Some(true)) => Direction::UpRight,
+        (Some(false), Some(false)) => Direction::DownLeft,
+        (Some(true), Some(false)) => Direction::DownRight,
+    };
+
+    // An axis that isn't moving still needs a valid speed.
+    Command::PanTiltDrive {
+        direction,
+        pan_speed: pan_speed.map_or(1, |i| i as u8 + 1),
+        tilt_speed: tilt_speed.map_or(1, |i| i as u8 + 1),
+    }
+}
+
+/// The command that starts a synchronized move, and the variable speed to zoom at.
+#[derive(Debug, PartialEq)]
+struct Plan {
+    pan_tilt: Command,
+    zoom: Option<u8>,
+    /// How long the slowest axis takes to arrive.
+    duration: Duration,
+}
+
+/// Picks speeds that take every axis from `from` to `to` in the same time, which is
+/// `duration` unless some axis can't go that fast.
+fn plan(
+    from: (PanTiltValue, u16),
+    to: (PanTiltValue, u16),
+    duration: Duration,
+    speeds: &SpeedTable,
+) -> Plan {
+    let pan = to.0.pan as f64 - from.0.pan as f64;
+    let tilt = to.0.tilt as f64 - from.0.tilt as f64;
+    let zoom = to.1 as f64 - from.1 as f64;
+
+    let secs = duration
+        .as_secs_f64()
+        .max(SpeedTable::shortest(&speeds.pan, pan))
+        .max(SpeedTable::shortest(&speeds.tilt, tilt))
+        .max(SpeedTable::shortest(&speeds.zoom, zoom));
+    let rate = |distance: f64| {
+        if secs > 0.0 {
+            distance / secs
+        } else {
+            f64::INFINITY
+        }
+    };
+
+    // Axes that don't need to move at all still need a valid speed.
+    let speed = |speeds: &[f64], distance: f64| {
+        SpeedTable::nearest(speeds, rate(distance)).map_or(1, |i| i as u8 + 1)
+    };
+    let pan_tilt = Command::PanTiltAbsolute {
+        pan_speed: speed(&speeds.pan, pan),
+        tilt_speed: speed(&speeds.tilt, tilt),
+        position: to.0,
+    };
+
+    let zoom = if zoom == 0.0 {
+        None
+    } else {
+        // Even the slowest zoom speed is used for the shortest of zooms.
+        Some(SpeedTable::nearest(&speeds.zoom, rate(zoom)).map_or(0, |i| i as u8))
+    };
+
+    Plan {
+        pan_tilt,
+        zoom,
+        duration: Duration::from_secs_f64(secs),
+    }
+}
+
+impl Camera {
+    /// Moves pan, tilt and zoom to a new position together, so that they all arrive in
+    /// about `duration`, or as soon as the slowest of them can.
+    ///
+    /// The zoom is made with `Zoom::set_at_speed`. This returns once pan, tilt and zoom
+    /// have all arrived, whichever of them had to move, and fails with `Error::Timeout` if
+    /// pan and tilt stop short of the target instead.
+    pub fn move_to(
+        &mut self,
+        pan_tilt: PanTiltValue,
+        zoom: u16,
+        duration: Duration,
+        speeds: &SpeedTable,
+    ) -> Result<()> {
+        let from = (self.pan_tilt().get()?, self.zoom().get()?);
+        let plan = plan(from, (pan_tilt, zoom), duration, speeds);
+
+        // The axes get a second's grace over the plan before they're given up on.
+        let timeout = plan.duration + Duration::from_secs(1);
+
+        execute(&mut self.iface, self.address, plan.pan_tilt)?;
+        if let Some(speed) = plan.zoom {
+            self.zoom().set_at_speed(zoom, speed, timeout)?;
+        }
+
+        let settled = self.pan_tilt().wait_for_position(pan_tilt, 0, timeout)?;
+        if !settled.reached {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::ErrorKind;
+    use crate::sim::{Fault, FaultSchedule};
+    use crate::testing::{assert_close, camera, sim_camera};
+
+    fn keyframe(secs: f64, pan: i16, zoom: u16, easing: Easing) -> Keyframe {
+        Keyframe {
+            at: Duration::from_secs_f64(secs),
+            pan_tilt: PanTiltValue { pan, tilt: 0 },
+            zoom,
+            focus: None,
+            easing,
+        }
+    }
+
+    #[test]
+    fn test_easing() {
+        for &easing in &[
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_close(easing.apply(0.0), 0.0);
+            assert_close(easing.apply(1.0), 1.0);
+        }
+
+        assert_close(Easing::EaseIn.apply(0.5), 0.25);
+        assert_close(Easing::EaseOut.apply(0.5), 0.75);
+        assert_close(Easing::EaseInOut.apply(0.5), 0.5);
+        assert!(Easing::EaseInOut.apply(0.1) < 0.1);
+    }
+
+    #[test]
+    fn test_sample() {
+        let timeline = Timeline::new(vec![
+            keyframe(0.0, 0, 0, Easing::Linear),
+            keyframe(1.0, 100, 0x1000, Easing::Linear),
+            keyframe(3.0, -100, 0x1000, Easing::EaseIn),
+        ])
+        .unwrap();
+
+        assert_close(timeline.sample(-1.0).pan, 0.0);
+        assert_close(timeline.sample(0.5).pan, 50.0);
+        assert_close(timeline.sample(0.5).zoom, 2048.0);
+        assert_close(timeline.sample(2.0).pan, 50.0);
+        assert_close(timeline.sample(5.0).pan, -100.0);
+        assert_eq!(timeline.sample(0.5).focus, None);
+        assert_eq!(timeline.duration(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_velocity() {
+        let timeline = Timeline::new(vec![
+            keyframe(0.0, 0, 0, Easing::Linear),
+            keyframe(2.0, 100, 0x1000, Easing::Linear),
+        ])
+        .unwrap();
+
+        let velocity = timeline.velocity(1.0);
+        assert!((velocity.pan - 50.0).abs() < 1e-3);
+        assert!((velocity.zoom - 2048.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_invalid_path() {
+        assert!(Timeline::new(vec![]).is_err());
+        assert!(Timeline::new(vec![
+            keyframe(1.0, 0, 0, Easing::Linear),
+            keyframe(1.0, 100, 0, Easing::Linear),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_nearest_speed() {
+        let speeds = [50.0, 100.0, 150.0];
+        assert_eq!(SpeedTable::nearest(&speeds, 20.0), None);
+        assert_eq!(SpeedTable::nearest(&speeds, 30.0), Some(0));
+        assert_eq!(SpeedTable::nearest(&speeds, -110.0), Some(1));
+        assert_eq!(SpeedTable::nearest(&speeds, 1000.0), Some(2));
+    }
+
+    #[test]
+    fn test_pan_tilt_drive() {
+        let speeds = Model::EVI_D70.speed_table();
+        assert_eq!(
+            pan_tilt_drive(-210.0, 10.0, &speeds),
+            Command::PanTiltDrive {
+                direction: Direction::Left,
+                pan_speed: 4,
+                tilt_speed: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let speeds = Model::EVI_D70.speed_table();
+        let from = (PanTiltValue { pan: 0, tilt: 0 }, 0x0000);
+        let to = (
+            PanTiltValue {
+                pan: 556,
+                tilt: -100,
+            },
+            0x2000,
+        );
+
+        let plan = plan(from, to, Duration::from_secs(2), &speeds);
+        assert_eq!(
+            plan.pan_tilt,
+            Command::PanTiltAbsolute {
+                pan_speed: 5,
+                tilt_speed: 1,
+                position: to.0,
+            }
+        );
+        assert_eq!(plan.zoom, Some(5));
+    }
+
+    #[test]
+    fn test_plan_too_fast() {
+        let speeds = Model::EVI_D70.speed_table();
+        let from = (PanTiltValue { pan: 0, tilt: 0 }, 0x1000);
+        let to = (
+            PanTiltValue {
+                pan: 2400,
+                tilt: 600,
+            },
+            0x0000,
+        );
+
+        // Panning at top speed takes two seconds, so tilt and zoom are slowed to match.
+        let plan = plan(from, to, Duration::from_millis(500), &speeds);
+        assert_eq!(
+            plan.pan_tilt,
+            Command::PanTiltAbsolute {
+                pan_speed: 0x18,
+                tilt_speed: 6,
+                position: to.0,
+            }
+        );
+        assert_eq!(plan.zoom, Some(2));
+    }
+
+    #[test]
+    fn test_move_to() {
+        let (mut camera, sim) = sim_camera();
+        let target = PanTiltValue {
+            pan: 250,
+            tilt: 100,
+        };
+
+        camera
+            .move_to(
+                target,
+                0x1000,
+                Duration::from_secs(1),
+                &Model::EVI_D70.speed_table(),
+            )
+            .unwrap();
+
+        let sim = sim.lock().unwrap();
+        assert_eq!(sim.pan_tilt(), target);
+        assert_eq!(sim.zoom(), 0x1000);
+        assert!(!sim.is_moving());
+    }
+
+    #[test]
+    fn test_move_to_without_zoom() {
+        let (mut camera, sim) = sim_camera();
+        let target = PanTiltValue {
+            pan: -500,
+            tilt: 200,
+        };
+
+        // Only pan and tilt move, and the call still waits for them to arrive.
+        camera
+            .move_to(
+                target,
+                0x0000,
+                Duration::from_millis(500),
+                &Model::EVI_D70.speed_table(),
+            )
+            .unwrap();
+
+        let sim = sim.lock().unwrap();
+        assert_eq!(sim.pan_tilt(), target);
+        assert!(!sim.is_moving());
+    }
+
+    #[test]
+    fn test_move_to_out_of_reach() {
+        let (mut camera, sim) = sim_camera();
+
+        // The camera can't tilt this far down, so it stops at the end of its range.
+        let target = PanTiltValue {
+            pan: 0,
+            tilt: -1000,
+        };
+        match camera.move_to(
+            target,
+            0x0000,
+            Duration::from_millis(500),
+            &Model::EVI_D70.speed_table(),
+        ) {
+            Err(Error::Timeout) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            sim.lock().unwrap().pan_tilt(),
+            PanTiltValue { pan: 0, tilt: -400 }
+        );
+    }
+
+    #[test]
+    fn test_invalid_speed_table() {
+        let lens = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let new =
+            |pan: Vec<f64>, zoom: Vec<f64>| SpeedTable::new(pan, vec![1.0], zoom, lens.clone());
+
+        assert!(new(vec![1.0, 2.0], lens.clone()).is_ok());
+        for (pan, zoom) in [
+            (vec![], lens.clone()),
+            (vec![0.0, 1.0], lens.clone()),
+            (vec![2.0, 1.0], lens.clone()),
+            (vec![1.0, f64::NAN], lens.clone()),
+            (vec![1.0], vec![]),
+            (vec![1.0], vec![1.0, 2.0]),
+        ] {
+            match new(pan, zoom) {
+                Err(Error::InvalidCalibration) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_play_stops_on_error() {
+        let (mut camera, sim) = sim_camera();
+
+        // The first tick starts pan-tilt and zoom driving, and the second fails to read the
+        // position back.
+        let faults = FaultSchedule::new().at(6, Fault::Error(ErrorKind::NotExecutable));
+        sim.lock().unwrap().set_faults(faults);
+
+        let timeline = Timeline::new(vec![keyframe(1.0, 500, 0x2000, Easing::Linear)]).unwrap();
+        match camera.play(&timeline, &Model::EVI_D70.speed_table()) {
+            Err(Error::Camera(ErrorKind::NotExecutable)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!sim.lock().unwrap().is_moving());
+    }
+
+    #[test]
+    fn test_play() {
+        let (mut camera, sim) = sim_camera();
+
+        let timeline = Timeline::new(vec![
+            keyframe(0.5, 100, 0x0800, Easing::EaseInOut),
+            keyframe(1.0, 250, 0x1000, Easing::Linear),
+        ])
+        .unwrap();
+
+        // Halfway through, the camera should be close to where the timeline puts it.
+        let probe = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(750));
+            sim.lock().unwrap().pan_tilt()
+        });
+
+        camera
+            .play(&timeline, &Model::EVI_D70.speed_table())
+            .unwrap();
+
+        let halfway = probe.join().unwrap();
+        assert!((halfway.pan - 175).abs() <= 50, "{:?}", halfway);
+
+        let settled = camera
+            .pan_tilt()
+            .wait_until_stopped(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(settled.position, PanTiltValue { pan: 250, tilt: 0 });
+        let zoom = camera
+            .zoom()
+            .wait_until_stopped(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(zoom.position, 0x1000);
+    }
+
+    #[test]
+    fn test_play_stops_focus_without_target() {
+        let mut camera = camera();
+
+        // Focus is driven towards the first keyframe, then left alone on the way to the
+        // second, which has no focus position.
+        let timeline = Timeline::new(vec![
+            Keyframe {
+                focus: Some(0x8000),
+                ..keyframe(0.5, 100, 0x0000, Easing::Linear)
+            },
+            keyframe(1.0, 200, 0x0000, Easing::Linear),
+        ])
+        .unwrap();
+
+        camera
+            .play(&timeline, &Model::EVI_D70.speed_table())
+            .unwrap();
+
+        let focus = camera.focus().get().unwrap();
+        assert!(focus < 0x8000, "{:#06x}", focus);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(camera.focus().get().unwrap(), focus);
+    }
+}
@@ -1,12 +1,21 @@
+use crate::{Error, Result};
+
 const PACKET_MAX_LEN: usize = 16;
 const PAYLOAD_MAX_LEN: usize = 12;
+const REQUEST_MIN_LEN: usize = 4;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Request {
     bytes: [u8; PACKET_MAX_LEN],
     len: usize,
 }
 
+impl Default for Request {
+    fn default() -> Self {
+        Request::new()
+    }
+}
+
 impl Request {
     pub fn new() -> Self {
         Request {
@@ -15,24 +24,34 @@ impl Request {
         }
     }
 
-    pub fn from_bytes(slice: &[u8]) -> Self {
+    /// Wraps an already-encoded packet, checking that it has a controller header and is
+    /// terminated by `0xff`.
+    pub fn from_bytes(slice: &[u8]) -> Result<Self> {
         let len = slice.len();
-        assert!(len <= PACKET_MAX_LEN);
+        if !(REQUEST_MIN_LEN..=PACKET_MAX_LEN).contains(&len) {
+            return Err(Error::InvalidRequest);
+        }
+        if slice[0] & 0xf0 != 0x80 || slice[len - 1] != 0xff {
+            return Err(Error::InvalidRequest);
+        }
 
         let mut bytes = [0; PACKET_MAX_LEN];
         bytes[..len].copy_from_slice(slice);
 
-        Request { bytes, len }
+        Ok(Request { bytes, len })
     }
 
     pub fn as_bytes(&self) -> &[u8] {
         &self.bytes[..self.len]
     }
 
-    pub fn address(mut self, addr: u8) -> Request {
-        assert!(addr <= 7);
+    /// Sets the destination camera address, which must be between 1 and 7.
+    pub fn address(mut self, addr: u8) -> Result<Request> {
+        if !(1..=7).contains(&addr) {
+            return Err(Error::InvalidAddress);
+        }
         self.bytes[0] = 0x80 | addr;
-        self
+        Ok(self)
     }
 
     pub fn command(self) -> Request {
@@ -43,6 +62,10 @@ impl Request {
         self.msg_type(MessageType::Inquiry)
     }
 
+    pub fn interface(self) -> Request {
+        self.category(Category::Interface)
+    }
+
     pub fn camera_1(self) -> Request {
         self.category(Category::Camera1)
     }
@@ -51,33 +74,55 @@ impl Request {
         self.category(Category::PanTilter)
     }
 
-    pub fn payload(mut self, payload: &[u8]) -> Request {
+    pub fn vendor(self) -> Request {
+        self.category(Category::Vendor)
+    }
+
+    pub fn category(mut self, category: Category) -> Request {
+        self.bytes[2] = category as u8;
+        self
+    }
+
+    /// Sets the payload and terminates the packet. The payload must not contain `0xff` and
+    /// can be at most 12 bytes long.
+    pub fn payload(mut self, payload: &[u8]) -> Result<Request> {
         let len = payload.len();
-        assert!(len <= PAYLOAD_MAX_LEN);
+        if len > PAYLOAD_MAX_LEN || payload.contains(&0xff) {
+            return Err(Error::InvalidPayload);
+        }
 
         self.bytes[3..3 + len].copy_from_slice(payload);
         self.len = len + 4;
         self.bytes[self.len - 1] = 0xff;
 
-        self
+        Ok(self)
     }
 
     fn msg_type(mut self, msg_type: MessageType) -> Request {
         self.bytes[1] = msg_type as u8;
         self
     }
-
-    fn category(mut self, category: Category) -> Request {
-        self.bytes[2] = category as u8;
-        self
-    }
 }
 
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum Category {
+pub enum Category {
+    Interface = 0x00,
     Camera1 = 0x04,
     PanTilter = 0x06,
+    Vendor = 0x7e,
+}
+
+impl Category {
+    pub fn from_u8(b: u8) -> Option<Self> {
+        match b {
+            0x00 => Some(Category::Interface),
+            0x04 => Some(Category::Camera1),
+            0x06 => Some(Category::PanTilter),
+            0x7e => Some(Category::Vendor),
+            _ => None,
+        }
+    }
 }
 
 #[repr(u8)]
@@ -89,25 +134,34 @@ enum MessageType {
 
 const REPLY_MIN_LEN: usize = 3;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Reply {
     bytes: [u8; PACKET_MAX_LEN],
     len: usize,
 }
 
 impl Reply {
-    pub fn parse(slice: &[u8]) -> Self {
+    pub fn parse(slice: &[u8]) -> Result<Self> {
         let len = slice.len();
-        assert!(len <= PACKET_MAX_LEN);
+        if !(REPLY_MIN_LEN..=PACKET_MAX_LEN).contains(&len) {
+            return Err(Error::InvalidReply);
+        }
+        if slice[0] & 0x80 == 0 || slice[len - 1] != 0xff {
+            return Err(Error::InvalidReply);
+        }
+        match slice[1] {
+            0x38 if len == REPLY_MIN_LEN => {}
+            0x40..=0x6f => {}
+            _ => return Err(Error::InvalidReply),
+        }
 
         let mut bytes = [0; PACKET_MAX_LEN];
         bytes[..len].copy_from_slice(slice);
 
-        Reply { bytes, len }
+        Ok(Reply { bytes, len })
     }
 
     pub fn as_bytes(&self) -> &[u8] {
-        assert!(self.len >= REPLY_MIN_LEN);
         &self.bytes[..self.len]
     }
 
@@ -119,14 +173,17 @@ impl Reply {
         self.as_bytes()[1] & 0x0f
     }
 
-    pub fn message(&self) -> Message {
+    pub fn message(&self) -> Message<'_> {
         let bytes = self.as_bytes();
 
+        if bytes[1] == 0x38 {
+            return Message::NetworkChange;
+        }
+
         match bytes[1] & 0xf0 {
             0x40 => Message::Ack,
             0x50 => Message::Completion(self.payload()),
-            0x60 => Message::Error(ErrorKind::from_u8(bytes[2])),
-            _ => unimplemented!(),
+            _ => Message::Error(ErrorKind::from_u8(bytes[2])),
         }
     }
 
@@ -141,6 +198,8 @@ pub enum Message<'a> {
     Ack,
     Completion(&'a [u8]),
     Error(ErrorKind),
+    /// Sent unprompted by a camera when its address or the bus configuration changes.
+    NetworkChange,
 }
 
 #[repr(u8)]
@@ -194,32 +253,32 @@ mod tests {
 
     #[test]
     fn test_reply_address() {
-        let reply = Reply::parse(&[0x90, 0x41, 0xff]);
+        let reply = Reply::parse(&[0x90, 0x41, 0xff]).unwrap();
         assert_eq!(reply.address(), 0x01);
     }
 
     #[test]
     fn test_reply_socket() {
-        let reply = Reply::parse(&[0x90, 0x52, 0x02, 0xff]);
+        let reply = Reply::parse(&[0x90, 0x52, 0x02, 0xff]).unwrap();
         assert_eq!(reply.socket(), 0x02);
     }
 
     #[test]
     fn test_ack_message() {
-        let reply = Reply::parse(&[0x90, 0x41, 0xff]);
+        let reply = Reply::parse(&[0x90, 0x41, 0xff]).unwrap();
         assert_eq!(reply.message(), Message::Ack);
     }
 
     #[test]
     fn test_empty_completion_message() {
-        let reply = Reply::parse(&[0x90, 0x51, 0xff]);
+        let reply = Reply::parse(&[0x90, 0x51, 0xff]).unwrap();
         assert_eq!(reply.message(), Message::Completion(&[]));
     }
 
     #[test]
     fn test_inquiry_reply_message() {
         let bytes = &[0x90, 0x50, 0x00, 0x01, 0x0b, 0x0c, 0xff];
-        let reply = Reply::parse(bytes);
+        let reply = Reply::parse(bytes).unwrap();
 
         assert_eq!(
             reply.message(),
@@ -229,7 +288,60 @@ mod tests {
 
     #[test]
     fn test_error_message() {
-        let reply = Reply::parse(&[0x90, 0x60, 0x02, 0xff]);
+        let reply = Reply::parse(&[0x90, 0x60, 0x02, 0xff]).unwrap();
         assert_eq!(reply.message(), Message::Error(ErrorKind::Syntax));
     }
+
+    #[test]
+    fn test_request_builder() {
+        let req = Request::new()
+            .address(1)
+            .unwrap()
+            .command()
+            .vendor()
+            .payload(&[0x01, 0x02])
+            .unwrap();
+
+        assert_eq!(req.as_bytes(), &[0x81, 0x01, 0x7e, 0x01, 0x02, 0xff]);
+    }
+
+    #[test]
+    fn test_request_invalid_address() {
+        assert!(Request::new().address(0).is_err());
+        assert!(Request::new().address(8).is_err());
+    }
+
+    #[test]
+    fn test_request_invalid_payload() {
+        assert!(Request::new().payload(&[0; 13]).is_err());
+        assert!(Request::new().payload(&[0x01, 0xff]).is_err());
+    }
+
+    #[test]
+    fn test_request_from_bytes() {
+        let req = Request::from_bytes(&[0x81, 0x09, 0x00, 0x02, 0xff]).unwrap();
+        assert_eq!(req.as_bytes(), &[0x81, 0x09, 0x00, 0x02, 0xff]);
+
+        assert!(Request::from_bytes(&[0x81, 0x09, 0x00, 0x02]).is_err());
+        assert!(Request::from_bytes(&[0x90, 0x09, 0x00, 0x02, 0xff]).is_err());
+    }
+
+    #[test]
+    fn test_category_from_u8() {
+        assert_eq!(Category::from_u8(0x7e), Some(Category::Vendor));
+        assert_eq!(Category::from_u8(0x05), None);
+    }
+
+    #[test]
+    fn test_network_change_message() {
+        let reply = Reply::parse(&[0x90, 0x38, 0xff]).unwrap();
+        assert_eq!(reply.message(), Message::NetworkChange);
+    }
+
+    #[test]
+    fn test_invalid_reply() {
+        assert!(Reply::parse(&[0x90, 0xff]).is_err());
+        assert!(Reply::parse(&[0x90, 0x41, 0x00]).is_err());
+        assert!(Reply::parse(&[0x90, 0xc1, 0xff]).is_err());
+    }
 }
@@ -0,0 +1,54 @@
+//! Number and duration parsing shared by the cue list and script formats.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// Parses a decimal or `0x` hex number, which may be negative.
+pub(crate) fn parse_number<T: TryFrom<i64>>(s: &str) -> Option<T> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+
+    let n = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    T::try_from(if negative { -n } else { n }).ok()
+}
+
+/// Parses a duration such as `500ms`, `2s` or `1.5s`.
+pub(crate) fn parse_duration(s: &str) -> Option<Duration> {
+    let (value, scale) = match s.strip_suffix("ms") {
+        Some(ms) => (ms, 1e-3),
+        None => (s.strip_suffix('s')?, 1.0),
+    };
+
+    let secs = value.parse::<f64>().ok()? * scale;
+    if secs.is_finite() && secs >= 0.0 {
+        Some(Duration::from_secs_f64(secs))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number::<i16>("-0x40"), Some(-64));
+        assert_eq!(parse_number::<u16>("4096"), Some(4096));
+        assert_eq!(parse_number::<u8>("256"), None);
+        assert_eq!(parse_number::<u8>("-1"), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+}
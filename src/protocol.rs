@@ -0,0 +1,597 @@
+use crate::commands::{PanTiltValue, PAN_TILT_VALUE_LEN};
+use crate::packet::{Category, Request};
+use crate::status::{CameraControlStatus, EnlargementStatus, LensStatus, OtherStatus};
+use crate::{Error, Result};
+
+const MSG_COMMAND: u8 = 0x01;
+const MSG_INQUIRY: u8 = 0x09;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    Stop,
+}
+
+impl Direction {
+    fn to_bytes(self) -> [u8; 2] {
+        match self {
+            Direction::Up => [0x03, 0x01],
+            Direction::Down => [0x03, 0x02],
+            Direction::Left => [0x01, 0x03],
+            Direction::Right => [0x02, 0x03],
+            Direction::UpLeft => [0x01, 0x01],
+            Direction::UpRight => [0x02, 0x01],
+            Direction::DownLeft => [0x01, 0x02],
+            Direction::DownRight => [0x02, 0x02],
+            Direction::Stop => [0x03, 0x03],
+        }
+    }
+
+    fn from_bytes(pan: u8, tilt: u8) -> Option<Self> {
+        match (pan, tilt) {
+            (0x03, 0x01) => Some(Direction::Up),
+            (0x03, 0x02) => Some(Direction::Down),
+            (0x01, 0x03) => Some(Direction::Left),
+            (0x02, 0x03) => Some(Direction::Right),
+            (0x01, 0x01) => Some(Direction::UpLeft),
+            (0x02, 0x01) => Some(Direction::UpRight),
+            (0x01, 0x02) => Some(Direction::DownLeft),
+            (0x02, 0x02) => Some(Direction::DownRight),
+            (0x03, 0x03) => Some(Direction::Stop),
+            _ => None,
+        }
+    }
+}
+
+/// A command that changes the state of the camera and is answered with an empty completion.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    PanTiltDrive {
+        direction: Direction,
+        pan_speed: u8,
+        tilt_speed: u8,
+    },
+    PanTiltAbsolute {
+        pan_speed: u8,
+        tilt_speed: u8,
+        position: PanTiltValue,
+    },
+    PanTiltRelative {
+        pan_speed: u8,
+        tilt_speed: u8,
+        position: PanTiltValue,
+    },
+    PanTiltHome,
+    ZoomDirect(u16),
+    ZoomStop,
+    /// Zooms in at a speed from 0 (slowest) to 7 until stopped.
+    ZoomTele(u8),
+    /// Zooms out at a speed from 0 (slowest) to 7 until stopped.
+    ZoomWide(u8),
+    FocusDirect(u16),
+    FocusStop,
+    /// Focuses further away at a speed from 0 (slowest) to 7 until stopped.
+    FocusFar(u8),
+    /// Focuses closer at a speed from 0 (slowest) to 7 until stopped.
+    FocusNear(u8),
+    /// Switches between autofocus, when true, and manual focus.
+    FocusAuto(bool),
+    /// Focuses once on whatever is in the middle of the picture.
+    FocusOnePush,
+    PresetReset(u8),
+    PresetSet(u8),
+    PresetRecall(u8),
+    /// Sets the pan-tilt speed used by later preset recalls, from 0x01 up to a
+    /// model-dependent maximum. This is a Sony extension.
+    PresetSpeed(u8),
+    Power(bool),
+}
+
+impl Command {
+    pub fn encode(&self, address: u8) -> Result<Request> {
+        let mut payload = [0; 11];
+        let len = self.encode_payload(&mut payload);
+
+        Request::new()
+            .address(address)?
+            .command()
+            .category(self.category())
+            .payload(&payload[..len])
+    }
+
+    /// Decodes a complete request packet, returning `None` if it isn't a known command.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (category, payload) = split_request(bytes, MSG_COMMAND)?;
+
+        match (category, payload) {
+            (Category::PanTilter, [0x01, ps, ts, p, t]) => Some(Command::PanTiltDrive {
+                direction: Direction::from_bytes(*p, *t)?,
+                pan_speed: *ps,
+                tilt_speed: *ts,
+            }),
+            (Category::PanTilter, [0x02, ps, ts, pos @ ..]) if is_position(pos) => {
+                Some(Command::PanTiltAbsolute {
+                    pan_speed: *ps,
+                    tilt_speed: *ts,
+                    position: PanTiltValue::from_bytes(pos),
+                })
+            }
+            (Category::PanTilter, [0x03, ps, ts, pos @ ..]) if is_position(pos) => {
+                Some(Command::PanTiltRelative {
+                    pan_speed: *ps,
+                    tilt_speed: *ts,
+                    position: PanTiltValue::from_bytes(pos),
+                })
+            }
+            (Category::PanTilter, [0x04]) => Some(Command::PanTiltHome),
+            (Category::Camera1, [0x47, val @ ..]) if val.len() == 4 && is_nibbles(val) => {
+                Some(Command::ZoomDirect(u16_from_nibbles(val)))
+            }
+            (Category::Camera1, [0x48, val @ ..]) if val.len() == 4 && is_nibbles(val) => {
+                Some(Command::FocusDirect(u16_from_nibbles(val)))
+            }
+            (Category::Camera1, [0x07, 0x00]) => Some(Command::ZoomStop),
+            (Category::Camera1, [0x07, b @ 0x20..=0x27]) => Some(Command::ZoomTele(b & 0x0f)),
+            (Category::Camera1, [0x07, b @ 0x30..=0x37]) => Some(Command::ZoomWide(b & 0x0f)),
+            (Category::Camera1, [0x08, 0x00]) => Some(Command::FocusStop),
+            (Category::Camera1, [0x08, b @ 0x20..=0x27]) => Some(Command::FocusFar(b & 0x0f)),
+            (Category::Camera1, [0x08, b @ 0x30..=0x37]) => Some(Command::FocusNear(b & 0x0f)),
+            (Category::Camera1, [0x38, 0x02]) => Some(Command::FocusAuto(true)),
+            (Category::Camera1, [0x38, 0x03]) => Some(Command::FocusAuto(false)),
+            (Category::Camera1, [0x18, 0x01]) => Some(Command::FocusOnePush),
+            (Category::Camera1, [0x3f, 0x00, num]) => Some(Command::PresetReset(*num)),
+            (Category::Camera1, [0x3f, 0x01, num]) => Some(Command::PresetSet(*num)),
+            (Category::Camera1, [0x3f, 0x02, num]) => Some(Command::PresetRecall(*num)),
+            (Category::Vendor, [0x01, 0x0b, speed]) => Some(Command::PresetSpeed(*speed)),
+            (Category::Camera1, [0x00, 0x02]) => Some(Command::Power(true)),
+            (Category::Camera1, [0x00, 0x03]) => Some(Command::Power(false)),
+            _ => None,
+        }
+    }
+
+    pub fn category(&self) -> Category {
+        match self {
+            Command::PanTiltDrive { .. }
+            | Command::PanTiltAbsolute { .. }
+            | Command::PanTiltRelative { .. }
+            | Command::PanTiltHome => Category::PanTilter,
+            Command::ZoomDirect(_)
+            | Command::ZoomStop
+            | Command::ZoomTele(_)
+            | Command::ZoomWide(_)
+            | Command::FocusDirect(_)
+            | Command::FocusStop
+            | Command::FocusFar(_)
+            | Command::FocusNear(_)
+            | Command::FocusAuto(_)
+            | Command::FocusOnePush
+            | Command::PresetReset(_)
+            | Command::PresetSet(_)
+            | Command::PresetRecall(_)
+            | Command::Power(_) => Category::Camera1,
+            Command::PresetSpeed(_) => Category::Vendor,
+        }
+    }
+
+    fn encode_payload(&self, buf: &mut [u8; 11]) -> usize {
+        match *self {
+            Command::PanTiltDrive {
+                direction,
+                pan_speed,
+                tilt_speed,
+            } => {
+                buf[..3].copy_from_slice(&[0x01, pan_speed, tilt_speed]);
+                buf[3..5].copy_from_slice(&direction.to_bytes());
+                5
+            }
+            Command::PanTiltAbsolute {
+                pan_speed,
+                tilt_speed,
+                position,
+            } => {
+                buf[..3].copy_from_slice(&[0x02, pan_speed, tilt_speed]);
+                buf[3..].copy_from_slice(&position.to_bytes());
+                11
+            }
+            Command::PanTiltRelative {
+                pan_speed,
+                tilt_speed,
+                position,
+            } => {
+                buf[..3].copy_from_slice(&[0x03, pan_speed, tilt_speed]);
+                buf[3..].copy_from_slice(&position.to_bytes());
+                11
+            }
+            Command::PanTiltHome => {
+                buf[0] = 0x04;
+                1
+            }
+            Command::ZoomDirect(val) => {
+                buf[0] = 0x47;
+                buf[1..5].copy_from_slice(&u16_to_nibbles(val));
+                5
+            }
+            Command::FocusDirect(val) => {
+                buf[0] = 0x48;
+                buf[1..5].copy_from_slice(&u16_to_nibbles(val));
+                5
+            }
+            Command::ZoomStop => drive_payload(buf, 0x07, 0x00),
+            Command::ZoomTele(speed) => drive_payload(buf, 0x07, 0x20 | speed.min(7)),
+            Command::ZoomWide(speed) => drive_payload(buf, 0x07, 0x30 | speed.min(7)),
+            Command::FocusStop => drive_payload(buf, 0x08, 0x00),
+            Command::FocusFar(speed) => drive_payload(buf, 0x08, 0x20 | speed.min(7)),
+            Command::FocusNear(speed) => drive_payload(buf, 0x08, 0x30 | speed.min(7)),
+            Command::FocusAuto(on) => drive_payload(buf, 0x38, if on { 0x02 } else { 0x03 }),
+            Command::FocusOnePush => drive_payload(buf, 0x18, 0x01),
+            Command::PresetReset(num) => preset_payload(buf, 0x00, num),
+            Command::PresetSet(num) => preset_payload(buf, 0x01, num),
+            Command::PresetRecall(num) => preset_payload(buf, 0x02, num),
+            Command::PresetSpeed(speed) => {
+                buf[..3].copy_from_slice(&[0x01, 0x0b, speed]);
+                3
+            }
+            Command::Power(on) => {
+                buf[..2].copy_from_slice(&[0x00, if on { 0x02 } else { 0x03 }]);
+                2
+            }
+        }
+    }
+}
+
+fn drive_payload(buf: &mut [u8; 11], op: u8, arg: u8) -> usize {
+    buf[..2].copy_from_slice(&[op, arg]);
+    2
+}
+
+fn preset_payload(buf: &mut [u8; 11], op: u8, num: u8) -> usize {
+    buf[..3].copy_from_slice(&[0x3f, op, num & 0x0f]);
+    3
+}
+
+/// A request for some piece of the camera's state.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Inquiry {
+    PanTiltPosition,
+    ZoomPosition,
+    FocusPosition,
+    Power,
+    Version,
+    LensBlock,
+    CameraBlock,
+    OtherBlock,
+    EnlargementBlock,
+}
+
+impl Inquiry {
+    pub fn encode(&self, address: u8) -> Result<Request> {
+        Request::new()
+            .address(address)?
+            .inquiry()
+            .category(self.category())
+            .payload(self.payload())
+    }
+
+    /// Decodes a complete request packet, returning `None` if it isn't a known inquiry.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (category, payload) = split_request(bytes, MSG_INQUIRY)?;
+
+        match (category, payload) {
+            (Category::PanTilter, [0x12]) => Some(Inquiry::PanTiltPosition),
+            (Category::Camera1, [0x47]) => Some(Inquiry::ZoomPosition),
+            (Category::Camera1, [0x48]) => Some(Inquiry::FocusPosition),
+            (Category::Camera1, [0x00]) => Some(Inquiry::Power),
+            (Category::Interface, [0x02]) => Some(Inquiry::Version),
+            (Category::Vendor, [0x7e, 0x00]) => Some(Inquiry::LensBlock),
+            (Category::Vendor, [0x7e, 0x01]) => Some(Inquiry::CameraBlock),
+            (Category::Vendor, [0x7e, 0x02]) => Some(Inquiry::OtherBlock),
+            (Category::Vendor, [0x7e, 0x03]) => Some(Inquiry::EnlargementBlock),
+            _ => None,
+        }
+    }
+
+    pub fn category(&self) -> Category {
+        match self {
+            Inquiry::PanTiltPosition => Category::PanTilter,
+            Inquiry::ZoomPosition | Inquiry::FocusPosition | Inquiry::Power => Category::Camera1,
+            Inquiry::Version => Category::Interface,
+            Inquiry::LensBlock
+            | Inquiry::CameraBlock
+            | Inquiry::OtherBlock
+            | Inquiry::EnlargementBlock => Category::Vendor,
+        }
+    }
+
+    /// Decodes the payload of the completion sent in answer to this inquiry.
+    pub fn decode_reply(&self, payload: &[u8]) -> Result<InquiryReply> {
+        match self {
+            Inquiry::PanTiltPosition if payload.len() == PAN_TILT_VALUE_LEN => Ok(
+                InquiryReply::PanTiltPosition(PanTiltValue::from_bytes(payload)),
+            ),
+            Inquiry::ZoomPosition if payload.len() == 4 => {
+                Ok(InquiryReply::ZoomPosition(u16_from_nibbles(payload)))
+            }
+            Inquiry::FocusPosition if payload.len() == 4 => {
+                Ok(InquiryReply::FocusPosition(u16_from_nibbles(payload)))
+            }
+            Inquiry::Power => match payload {
+                [0x02] => Ok(InquiryReply::Power(true)),
+                [0x03] => Ok(InquiryReply::Power(false)),
+                _ => Err(Error::InvalidReply),
+            },
+            Inquiry::Version => match *payload {
+                [v0, v1, m0, m1, r0, r1, max_socket] => Ok(InquiryReply::Version(Version {
+                    vendor_id: u16::from_be_bytes([v0, v1]),
+                    model_id: u16::from_be_bytes([m0, m1]),
+                    rom_version: u16::from_be_bytes([r0, r1]),
+                    max_socket,
+                })),
+                _ => Err(Error::InvalidReply),
+            },
+            Inquiry::LensBlock => LensStatus::from_payload(payload)
+                .map(InquiryReply::LensBlock)
+                .ok_or(Error::InvalidReply),
+            Inquiry::CameraBlock => CameraControlStatus::from_payload(payload)
+                .map(InquiryReply::CameraBlock)
+                .ok_or(Error::InvalidReply),
+            Inquiry::OtherBlock => OtherStatus::from_payload(payload)
+                .map(InquiryReply::OtherBlock)
+                .ok_or(Error::InvalidReply),
+            Inquiry::EnlargementBlock => EnlargementStatus::from_payload(payload)
+                .map(InquiryReply::EnlargementBlock)
+                .ok_or(Error::InvalidReply),
+            _ => Err(Error::InvalidReply),
+        }
+    }
+
+    fn payload(&self) -> &'static [u8] {
+        match self {
+            Inquiry::PanTiltPosition => &[0x12],
+            Inquiry::ZoomPosition => &[0x47],
+            Inquiry::FocusPosition => &[0x48],
+            Inquiry::Power => &[0x00],
+            Inquiry::Version => &[0x02],
+            Inquiry::LensBlock => &[0x7e, 0x00],
+            Inquiry::CameraBlock => &[0x7e, 0x01],
+            Inquiry::OtherBlock => &[0x7e, 0x02],
+            Inquiry::EnlargementBlock => &[0x7e, 0x03],
+        }
+    }
+}
+
+/// The identity of a camera, as reported by the version inquiry.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Version {
+    pub vendor_id: u16,
+    pub model_id: u16,
+    pub rom_version: u16,
+    /// The highest socket number the camera can use to execute commands concurrently.
+    pub max_socket: u8,
+}
+
+/// The decoded answer to an `Inquiry`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InquiryReply {
+    PanTiltPosition(PanTiltValue),
+    ZoomPosition(u16),
+    FocusPosition(u16),
+    Power(bool),
+    Version(Version),
+    LensBlock(LensStatus),
+    CameraBlock(CameraControlStatus),
+    OtherBlock(OtherStatus),
+    EnlargementBlock(EnlargementStatus),
+}
+
+impl InquiryReply {
+    /// Encodes the payload of the completion that carries this reply.
+    pub fn to_payload(&self) -> Vec<u8> {
+        match *self {
+            InquiryReply::PanTiltPosition(val) => val.to_bytes().to_vec(),
+            InquiryReply::ZoomPosition(val) | InquiryReply::FocusPosition(val) => {
+                u16_to_nibbles(val).to_vec()
+            }
+            InquiryReply::Power(on) => vec![if on { 0x02 } else { 0x03 }],
+            InquiryReply::Version(version) => {
+                let mut payload = Vec::with_capacity(7);
+                payload.extend_from_slice(&version.vendor_id.to_be_bytes());
+                payload.extend_from_slice(&version.model_id.to_be_bytes());
+                payload.extend_from_slice(&version.rom_version.to_be_bytes());
+                payload.push(version.max_socket);
+                payload
+            }
+            InquiryReply::LensBlock(status) => status.to_payload().to_vec(),
+            InquiryReply::CameraBlock(status) => status.to_payload().to_vec(),
+            InquiryReply::OtherBlock(status) => status.to_payload().to_vec(),
+            InquiryReply::EnlargementBlock(status) => status.to_payload().to_vec(),
+        }
+    }
+}
+
+fn split_request(bytes: &[u8], msg_type: u8) -> Option<(Category, &[u8])> {
+    match bytes {
+        [addr, msg, category, payload @ .., 0xff] if addr & 0xf0 == 0x80 && *msg == msg_type => {
+            Category::from_u8(*category).map(|category| (category, payload))
+        }
+        _ => None,
+    }
+}
+
+fn is_position(bytes: &[u8]) -> bool {
+    bytes.len() == PAN_TILT_VALUE_LEN && is_nibbles(bytes)
+}
+
+fn is_nibbles(bytes: &[u8]) -> bool {
+    bytes.iter().all(|b| b & 0xf0 == 0)
+}
+
+pub(crate) fn u16_to_nibbles(val: u16) -> [u8; 4] {
+    [
+        ((val & 0xf000) >> 12) as u8,
+        ((val & 0x0f00) >> 8) as u8,
+        ((val & 0x00f0) >> 4) as u8,
+        (val & 0x000f) as u8,
+    ]
+}
+
+pub(crate) fn u16_from_nibbles(bytes: &[u8]) -> u16 {
+    assert_eq!(bytes.len(), 4);
+
+    let mut val = (bytes[0] as u16) << 12;
+    val |= (bytes[1] as u16) << 8;
+    val |= (bytes[2] as u16) << 4;
+    val |= bytes[3] as u16;
+    val
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(cmd: Command) {
+        let req = cmd.encode(1).unwrap();
+        assert_eq!(Command::decode(req.as_bytes()), Some(cmd));
+    }
+
+    #[test]
+    fn test_encode_drive() {
+        let cmd = Command::PanTiltDrive {
+            direction: Direction::Up,
+            pan_speed: 0x18,
+            tilt_speed: 0x14,
+        };
+
+        assert_eq!(
+            cmd.encode(1).unwrap().as_bytes(),
+            &[0x81, 0x01, 0x06, 0x01, 0x18, 0x14, 0x03, 0x01, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_encode_zoom_direct() {
+        let cmd = Command::ZoomDirect(0x1234);
+
+        assert_eq!(
+            cmd.encode(2).unwrap().as_bytes(),
+            &[0x82, 0x01, 0x04, 0x47, 0x01, 0x02, 0x03, 0x04, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_encode_zoom_drive() {
+        assert_eq!(
+            Command::ZoomTele(3).encode(1).unwrap().as_bytes(),
+            &[0x81, 0x01, 0x04, 0x07, 0x23, 0xff]
+        );
+        assert_eq!(
+            Command::FocusNear(9).encode(1).unwrap().as_bytes(),
+            &[0x81, 0x01, 0x04, 0x08, 0x37, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_command_roundtrip() {
+        let position = PanTiltValue {
+            pan: -1200,
+            tilt: 300,
+        };
+
+        roundtrip(Command::PanTiltDrive {
+            direction: Direction::DownLeft,
+            pan_speed: 0x01,
+            tilt_speed: 0x02,
+        });
+        roundtrip(Command::PanTiltAbsolute {
+            pan_speed: 0x01,
+            tilt_speed: 0x00,
+            position,
+        });
+        roundtrip(Command::PanTiltRelative {
+            pan_speed: 0x18,
+            tilt_speed: 0x14,
+            position,
+        });
+        roundtrip(Command::PanTiltHome);
+        roundtrip(Command::ZoomDirect(0x4000));
+        roundtrip(Command::ZoomStop);
+        roundtrip(Command::ZoomTele(7));
+        roundtrip(Command::ZoomWide(0));
+        roundtrip(Command::FocusDirect(0x1000));
+        roundtrip(Command::FocusStop);
+        roundtrip(Command::FocusFar(3));
+        roundtrip(Command::FocusNear(5));
+        roundtrip(Command::FocusAuto(true));
+        roundtrip(Command::FocusAuto(false));
+        roundtrip(Command::FocusOnePush);
+        roundtrip(Command::PresetReset(1));
+        roundtrip(Command::PresetSet(2));
+        roundtrip(Command::PresetRecall(3));
+        roundtrip(Command::PresetSpeed(0x18));
+        roundtrip(Command::Power(true));
+        roundtrip(Command::Power(false));
+    }
+
+    #[test]
+    fn test_decode_unknown_command() {
+        assert_eq!(Command::decode(&[0x81, 0x01, 0x04, 0x10, 0x02, 0xff]), None);
+        assert_eq!(
+            Command::decode(&[0x81, 0x01, 0x06, 0x02, 0x01, 0x01, 0xff]),
+            None
+        );
+        assert_eq!(Command::decode(&[0x81, 0x09, 0x04, 0x47, 0xff]), None);
+    }
+
+    #[test]
+    fn test_inquiry_roundtrip() {
+        let inquiries = &[
+            Inquiry::PanTiltPosition,
+            Inquiry::ZoomPosition,
+            Inquiry::FocusPosition,
+            Inquiry::Power,
+            Inquiry::Version,
+            Inquiry::LensBlock,
+            Inquiry::CameraBlock,
+            Inquiry::OtherBlock,
+            Inquiry::EnlargementBlock,
+        ];
+
+        for inq in inquiries {
+            let req = inq.encode(1).unwrap();
+            assert_eq!(Inquiry::decode(req.as_bytes()), Some(*inq));
+        }
+    }
+
+    #[test]
+    fn test_inquiry_reply_roundtrip() {
+        let reply = InquiryReply::PanTiltPosition(PanTiltValue { pan: -5, tilt: 17 });
+        let payload = reply.to_payload();
+
+        assert_eq!(
+            Inquiry::PanTiltPosition.decode_reply(&payload).unwrap(),
+            reply
+        );
+        assert!(Inquiry::ZoomPosition.decode_reply(&payload).is_err());
+    }
+
+    #[test]
+    fn test_decode_version_reply() {
+        let payload = &[0x00, 0x01, 0x05, 0x0a, 0x01, 0x20, 0x02];
+
+        assert_eq!(
+            Inquiry::Version.decode_reply(payload).unwrap(),
+            InquiryReply::Version(Version {
+                vendor_id: 0x0001,
+                model_id: 0x050a,
+                rom_version: 0x0120,
+                max_socket: 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::capture::{open_capture, Flow, Record};
+use crate::dissect::{dissect, hex};
+use crate::transport::Transport;
+use crate::Result;
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::thread;
+
+/// A transport that plays back a recorded session instead of talking to a camera.
+///
+/// Every request sent through it must match the next recorded request byte-for-byte,
+/// otherwise it panics. The replies recorded after that request are then returned in order.
+/// Dropping the transport before the whole capture has been replayed also panics, so that
+/// a test can't silently skip part of a session.
+pub struct ReplayTransport {
+    records: VecDeque<Record>,
+    replies: VecDeque<Vec<u8>>,
+}
+
+impl ReplayTransport {
+    pub fn new(records: Vec<Record>) -> Self {
+        ReplayTransport {
+            records: records.into(),
+            replies: VecDeque::new(),
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        open_capture(path).map(ReplayTransport::new)
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        let expected = match self.records.pop_front() {
+            Some(record) if record.flow == Flow::Sent => record.bytes,
+            Some(record) => panic!(
+                "sent {} ({}) but the capture has an unread reply {}",
+                hex(packet),
+                dissect(packet),
+                hex(&record.bytes)
+            ),
+            None => panic!(
+                "sent {} ({}) past the end of the capture",
+                hex(packet),
+                dissect(packet)
+            ),
+        };
+
+        assert!(
+            packet == &expected[..],
+            "sent {} ({}) but the capture expected {} ({})",
+            hex(packet),
+            dissect(packet),
+            hex(&expected),
+            dissect(&expected)
+        );
+
+        while let Some(record) = self.records.front() {
+            if record.flow == Flow::Sent {
+                break;
+            }
+            self.replies
+                .extend(self.records.pop_front().map(|r| r.bytes));
+        }
+
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.replies.pop_front() {
+            Some(reply) => {
+                let len = reply.len().min(buf.len());
+                buf[..len].copy_from_slice(&reply[..len]);
+                Ok(len)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no more replies in capture",
+            )),
+        }
+    }
+}
+
+impl Drop for ReplayTransport {
+    fn drop(&mut self) {
+        if !thread::panicking() && !self.records.is_empty() {
+            panic!(
+                "{} packets of the capture were never replayed, starting with {}",
+                self.records.len(),
+                hex(&self.records[0].bytes)
+            );
+        }
+    }
+}
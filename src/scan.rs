@@ -0,0 +1,135 @@
+use crate::commands;
+use crate::interface::Interface;
+use crate::packet::Request;
+use crate::protocol::Version;
+use crate::{Error, Result};
+use std::time::Duration;
+
+/// The baud rates cameras are most commonly set to, in the order they are tried.
+pub const SCAN_BAUD_RATES: [u32; 4] = [9600, 38400, 19200, 115200];
+
+/// Replies arrive within a few milliseconds, so there's no need to wait long on a port
+/// with nothing attached or a mismatched baud rate.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+const ADDRESS_SET: &[u8] = &[0x88, 0x30, 0x01, 0xff];
+
+/// A serial port with one or more cameras attached.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScanResult {
+    pub port: String,
+    pub baud_rate: u32,
+    /// The cameras on the bus, in address order.
+    pub cameras: Vec<FoundCamera>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FoundCamera {
+    pub address: u8,
+    /// The camera's version information, or `None` if it didn't answer the inquiry.
+    pub version: Option<Version>,
+}
+
+/// Looks for cameras on every serial port of the system.
+///
+/// Each port is probed at each of `SCAN_BAUD_RATES` in turn by assigning addresses with
+/// AddressSet, which renumbers the cameras on any bus it finds.
+pub fn scan() -> Result<Vec<ScanResult>> {
+    let ports = serialport::available_ports().map_err(|err| Error::Io(err.into()))?;
+    let mut results = Vec::new();
+
+    for info in ports {
+        for &baud_rate in SCAN_BAUD_RATES.iter() {
+            // Ports that are busy or can't be opened are skipped altogether.
+            let iface =
+                match Interface::open_with_timeout(&info.port_name, baud_rate, PROBE_TIMEOUT) {
+                    Ok(iface) => iface,
+                    Err(_) => break,
+                };
+
+            if let Some(cameras) = probe(iface) {
+                results.push(ScanResult {
+                    port: info.port_name.clone(),
+                    baud_rate,
+                    cameras,
+                });
+                break;
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// Assigns addresses to the cameras on a bus and asks each one for its version.
+fn probe(mut iface: Interface) -> Option<Vec<FoundCamera>> {
+    let req = Request::from_bytes(ADDRESS_SET).ok()?;
+    iface.send_request(&req).ok()?;
+
+    // The last camera on the bus replies with the address after its own.
+    let count = match iface.recv_packet().ok()?.as_slice() {
+        [0x88, 0x30, next @ 2..=8, 0xff] => next - 1,
+        _ => return None,
+    };
+
+    let cameras = (1..=count)
+        .map(|address| FoundCamera {
+            address,
+            version: commands::version(&mut iface, address).ok(),
+        })
+        .collect();
+
+    Some(cameras)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    #[test]
+    fn test_probe() {
+        let mut mock = MockTransport::new();
+        mock.expect(ADDRESS_SET).reply(&[0x88, 0x30, 0x03, 0xff]);
+        mock.expect(&[0x81, 0x09, 0x00, 0x02, 0xff])
+            .reply(&[0x90, 0x50, 0x00, 0x20, 0x05, 0x0a, 0x01, 0x00, 0x02, 0xff]);
+        mock.expect(&[0x82, 0x09, 0x00, 0x02, 0xff])
+            .reply(&[0xa0, 0x60, 0x02, 0xff]);
+
+        let cameras = probe(Interface::new(Box::new(mock))).unwrap();
+        assert_eq!(
+            cameras,
+            vec![
+                FoundCamera {
+                    address: 1,
+                    version: Some(Version {
+                        vendor_id: 0x0020,
+                        model_id: 0x050a,
+                        rom_version: 0x0100,
+                        max_socket: 2,
+                    }),
+                },
+                FoundCamera {
+                    address: 2,
+                    version: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_probe_nothing_attached() {
+        let mut mock = MockTransport::new();
+        mock.expect(ADDRESS_SET);
+
+        assert_eq!(probe(Interface::new(Box::new(mock))), None);
+    }
+
+    #[test]
+    fn test_probe_garbage() {
+        let mut mock = MockTransport::new();
+        mock.expect(ADDRESS_SET).reply(&[0x1c, 0xe3, 0xff]);
+
+        assert_eq!(probe(Interface::new(Box::new(mock))), None);
+    }
+}
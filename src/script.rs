@@ -0,0 +1,474 @@
+//! A small language for scripting sequences of commands.
+//!
+//! Statements are separated by semicolons or new lines, and `#` starts a comment:
+//!
+//! ```text
+//! recall 3; wait 2s
+//! zoom to 0x2000 at speed 5; focus one-push
+//!
+//! repeat 3 {
+//!     camera left { pan-tilt to -400 120 at speed 0x0c 0x0a }
+//!     wait 500ms
+//!     camera right { recall 2 speed 0x10; zoom to 0x1000 }
+//! }
+//! ```
+//!
+//! Statements apply to the first camera added to the runner, except inside a
+//! `camera NAME { ... }` block. The statements are:
+//!
+//! - `recall N [speed S]` and `store N` for presets
+//! - `pan-tilt to PAN TILT [at speed P T]`, `pan-tilt home` and `pan-tilt stop`
+//! - `zoom to N [at speed S]`, `zoom tele|wide [at speed S]` and `zoom stop`
+//! - `focus to N`, `focus auto`, `focus manual`, `focus one-push` and `focus stop`
+//! - `power on|off`
+//! - `wait DURATION`, such as `500ms` or `1.5s`
+//! - `repeat N { ... }` and `camera NAME { ... }`
+//!
+//! Numbers may be decimal or `0x` hex.
+
+use crate::action::{perform, Action};
+use crate::commands::PanTiltValue;
+use crate::parse::{parse_duration, parse_number};
+use crate::{Camera, Error, Result};
+use std::thread;
+use std::time::Duration;
+
+/// The speed continuous zooms use when none is given.
+const DEFAULT_ZOOM_SPEED: u8 = 3;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Statement {
+    Action(Action),
+    Wait(Duration),
+    Repeat(u32, Vec<Statement>),
+    /// Statements that apply to the named camera.
+    Camera(String, Vec<Statement>),
+}
+
+/// A parsed script, ready to run.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Script {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    End,
+}
+
+impl Script {
+    /// Parses a script, failing with `Error::InvalidScript` and the number of the
+    /// offending line.
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text);
+        let mut parser = Parser { tokens, pos: 0 };
+        let statements = parser.block(false)?;
+        Ok(Script { statements })
+    }
+}
+
+fn tokenize(text: &str) -> Vec<(Token, usize)> {
+    let mut tokens = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut word = String::new();
+
+        for c in line.chars().chain(Some('\n')) {
+            let token = match c {
+                '{' => Some(Token::Open),
+                '}' => Some(Token::Close),
+                ';' | '\n' => Some(Token::End),
+                c if c.is_whitespace() => None,
+                c => {
+                    word.push(c);
+                    continue;
+                }
+            };
+
+            if !word.is_empty() {
+                tokens.push((Token::Word(word.split_off(0)), i + 1));
+            }
+            tokens.extend(token.map(|t| (t, i + 1)));
+        }
+    }
+
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Parses statements up to the end of the script or, if `nested`, a closing brace.
+    fn block(&mut self, nested: bool) -> Result<Vec<Statement>> {
+        let mut statements = Vec::new();
+
+        loop {
+            match self.tokens.get(self.pos) {
+                None if nested => return Err(self.error()),
+                None => return Ok(statements),
+                Some((Token::Close, _)) if nested => {
+                    self.pos += 1;
+                    return Ok(statements);
+                }
+                Some((Token::End, _)) => self.pos += 1,
+                Some(_) => statements.push(self.statement()?),
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let line = self.line();
+        let mut words = Vec::new();
+        while let Some((Token::Word(word), _)) = self.tokens.get(self.pos) {
+            words.push(word.clone());
+            self.pos += 1;
+        }
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let invalid = || Error::InvalidScript(line);
+
+        let statement = match words.as_slice() {
+            ["repeat", count] => {
+                let count = parse_number(count).ok_or_else(invalid)?;
+                Statement::Repeat(count, self.body()?)
+            }
+            ["camera", name] => Statement::Camera(name.to_string(), self.body()?),
+            ["wait", duration] => Statement::Wait(parse_duration(duration).ok_or_else(invalid)?),
+            words => Statement::Action(parse_action(words).ok_or_else(invalid)?),
+        };
+
+        // A statement has to be followed by a separator or the end of its block.
+        match self.tokens.get(self.pos) {
+            None | Some((Token::End, _)) | Some((Token::Close, _)) => Ok(statement),
+            Some(_) => Err(self.error()),
+        }
+    }
+
+    fn body(&mut self) -> Result<Vec<Statement>> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Open, _)) => {
+                self.pos += 1;
+                self.block(true)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn line(&self) -> usize {
+        match self.tokens.get(self.pos).or_else(|| self.tokens.last()) {
+            Some(&(_, line)) => line,
+            None => 1,
+        }
+    }
+
+    fn error(&self) -> Error {
+        Error::InvalidScript(self.line())
+    }
+}
+
+fn parse_action(words: &[&str]) -> Option<Action> {
+    let action = match words {
+        ["recall", preset] => Action::Recall {
+            preset: parse_number(preset)?,
+            speed: None,
+        },
+        ["recall", preset, "speed", speed] => Action::Recall {
+            preset: parse_number(preset)?,
+            speed: Some(parse_number(speed)?),
+        },
+        ["store", preset] => Action::Store(parse_number(preset)?),
+        ["pan-tilt", "to", pan, tilt, rest @ ..] => Action::Move {
+            pan_tilt: PanTiltValue {
+                pan: parse_number(pan)?,
+                tilt: parse_number(tilt)?,
+            },
+            speed: match rest {
+                [] => None,
+                ["at", "speed", pan, tilt] => Some((parse_number(pan)?, parse_number(tilt)?)),
+                _ => return None,
+            },
+        },
+        ["pan-tilt", "home"] => Action::Home,
+        ["pan-tilt", "stop"] => Action::PanTiltStop,
+        ["zoom", "to", pos] => Action::Zoom {
+            position: parse_number(pos)?,
+            speed: None,
+        },
+        ["zoom", "to", pos, "at", "speed", speed] => Action::Zoom {
+            position: parse_number(pos)?,
+            speed: Some(lens_speed(speed)?),
+        },
+        ["zoom", "tele"] => Action::ZoomTele(DEFAULT_ZOOM_SPEED),
+        ["zoom", "tele", "at", "speed", speed] => Action::ZoomTele(lens_speed(speed)?),
+        ["zoom", "wide"] => Action::ZoomWide(DEFAULT_ZOOM_SPEED),
+        ["zoom", "wide", "at", "speed", speed] => Action::ZoomWide(lens_speed(speed)?),
+        ["zoom", "stop"] => Action::ZoomStop,
+        ["focus", "to", pos] => Action::Focus(parse_number(pos)?),
+        ["focus", "auto"] => Action::FocusAuto(true),
+        ["focus", "manual"] => Action::FocusAuto(false),
+        ["focus", "one-push"] => Action::FocusOnePush,
+        ["focus", "stop"] => Action::FocusStop,
+        ["power", "on"] => Action::Power(true),
+        ["power", "off"] => Action::Power(false),
+        _ => return None,
+    };
+
+    Some(action)
+}
+
+fn lens_speed(s: &str) -> Option<u8> {
+    parse_number(s).filter(|&speed| speed <= 7)
+}
+
+/// Runs scripts against a set of named cameras.
+pub struct ScriptRunner {
+    /// Each camera's name, the connection it's reached through and its address there.
+    cameras: Vec<(String, usize, u8)>,
+    connections: Vec<Camera>,
+}
+
+impl Default for ScriptRunner {
+    fn default() -> Self {
+        ScriptRunner::new()
+    }
+}
+
+impl ScriptRunner {
+    pub fn new() -> Self {
+        ScriptRunner {
+            cameras: Vec::new(),
+            connections: Vec::new(),
+        }
+    }
+
+    /// Adds a camera for `camera NAME` blocks to refer to. The first camera added is the
+    /// one statements outside such blocks apply to.
+    pub fn add_camera(&mut self, name: &str, camera: Camera) {
+        let address = camera.address();
+        self.connections.push(camera);
+        self.cameras
+            .push((name.to_string(), self.connections.len() - 1, address));
+    }
+
+    /// Adds a camera at another address on the daisy-chained bus that the camera named
+    /// `bus` is on, so that both are scripted through the one connection.
+    pub fn add_camera_on_bus(&mut self, name: &str, bus: &str, address: u8) -> Result<()> {
+        if !(1..=7).contains(&address) {
+            return Err(Error::InvalidAddress);
+        }
+        let connection = match self.cameras.iter().find(|(n, ..)| n == bus) {
+            Some(&(_, connection, _)) => connection,
+            None => return Err(Error::UnknownCamera(bus.to_string())),
+        };
+
+        self.cameras.push((name.to_string(), connection, address));
+        Ok(())
+    }
+
+    pub fn camera(&mut self, name: &str) -> Option<&mut Camera> {
+        let &(_, connection, address) = self.cameras.iter().find(|(n, ..)| n == name)?;
+        let camera = &mut self.connections[connection];
+        // The address was checked when the camera was added, so this can't fail.
+        let _ = camera.set_address(address);
+        Some(camera)
+    }
+
+    /// Runs a script, stopping at the first command that fails. Every camera the script
+    /// names is checked before anything is sent, so that a misspelt name doesn't leave
+    /// the script half run.
+    pub fn run(&mut self, script: &Script) -> Result<()> {
+        let name = match self.cameras.first() {
+            Some((name, ..)) => name.clone(),
+            None => return Err(Error::NoCamera),
+        };
+        self.check_cameras(&script.statements)?;
+        self.run_block(&script.statements, &name)
+    }
+
+    fn check_cameras(&self, statements: &[Statement]) -> Result<()> {
+        for statement in statements {
+            match statement {
+                Statement::Repeat(_, body) => self.check_cameras(body)?,
+                Statement::Camera(name, body) => {
+                    if !self.cameras.iter().any(|(n, ..)| n == name) {
+                        return Err(Error::UnknownCamera(name.clone()));
+                    }
+                    self.check_cameras(body)?;
+                }
+                Statement::Action(_) | Statement::Wait(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn run_block(&mut self, statements: &[Statement], target: &str) -> Result<()> {
+        for statement in statements {
+            match statement {
+                Statement::Action(action) => {
+                    let camera = self
+                        .camera(target)
+                        .ok_or_else(|| Error::UnknownCamera(target.to_string()))?;
+                    perform(camera, *action)?;
+                }
+                Statement::Wait(duration) => thread::sleep(*duration),
+                Statement::Repeat(count, body) => {
+                    for _ in 0..*count {
+                        self.run_block(body, target)?;
+                    }
+                }
+                Statement::Camera(name, body) => self.run_block(body, name)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::testing::camera;
+
+    #[test]
+    fn test_parse() {
+        let script = Script::parse(
+            "recall 3; wait 2s
+             zoom to 0x2000 at speed 5; focus one-push # trigger autofocus once
+             repeat 2 {
+                 camera left { pan-tilt to -400 0x20 at speed 0x0c 0x0a }
+             }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.statements,
+            vec![
+                Statement::Action(Action::Recall {
+                    preset: 3,
+                    speed: None
+                }),
+                Statement::Wait(Duration::from_secs(2)),
+                Statement::Action(Action::Zoom {
+                    position: 0x2000,
+                    speed: Some(5)
+                }),
+                Statement::Action(Action::FocusOnePush),
+                Statement::Repeat(
+                    2,
+                    vec![Statement::Camera(
+                        "left".to_string(),
+                        vec![Statement::Action(Action::Move {
+                            pan_tilt: PanTiltValue {
+                                pan: -400,
+                                tilt: 0x20
+                            },
+                            speed: Some((0x0c, 0x0a)),
+                        })],
+                    )],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (text, line) in &[
+            ("recall", 1),
+            ("recall 3\nzoom to 0x2000 at speed 9", 2),
+            ("repeat 2 {\n  recall 1\n", 2),
+            ("recall 1 }", 1),
+            ("camera left recall 1", 1),
+            ("repeat x { recall 1 }", 1),
+            ("wait 2", 1),
+        ] {
+            match Script::parse(text) {
+                Err(Error::InvalidScript(n)) => assert_eq!(n, *line, "{:?}", text),
+                other => panic!("unexpected result for {:?}: {:?}", text, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let mut runner = ScriptRunner::new();
+        runner.add_camera("main", camera());
+        runner.add_camera("side", camera());
+
+        let script = Script::parse(
+            "zoom to 0x0800 at speed 7
+             store 1
+             zoom to 0
+             camera side { repeat 2 { focus auto; focus manual }; power off }
+             recall 1",
+        )
+        .unwrap();
+        runner.run(&script).unwrap();
+
+        let main = runner.camera("main").unwrap();
+        let zoom = main
+            .zoom()
+            .wait_until_stopped(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(zoom.position, 0x0800);
+        assert!(main.power().get().unwrap());
+
+        let side = runner.camera("side").unwrap();
+        assert!(!side.power().get().unwrap());
+    }
+
+    #[test]
+    fn test_unknown_camera() {
+        let mut runner = ScriptRunner::new();
+        runner.add_camera("main", camera());
+
+        // Nothing runs, not even the statements ahead of the unknown camera.
+        let script = Script::parse("power off; repeat 2 { camera other { recall 1 } }").unwrap();
+        match runner.run(&script) {
+            Err(Error::UnknownCamera(name)) => assert_eq!(name, "other"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(runner.camera("main").unwrap().power().get().unwrap());
+    }
+
+    #[test]
+    fn test_no_camera() {
+        let script = Script::parse("recall 1").unwrap();
+        match ScriptRunner::new().run(&script) {
+            Err(Error::NoCamera) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cameras_on_one_bus() {
+        let mut mock = MockTransport::new();
+        mock.expect(&[0x81, 0x01, 0x04, 0x00, 0x03, 0xff])
+            .reply(&[0x90, 0x41, 0xff])
+            .reply(&[0x90, 0x51, 0xff]);
+        mock.expect(&[0x82, 0x01, 0x04, 0x00, 0x03, 0xff])
+            .reply(&[0xa0, 0x41, 0xff])
+            .reply(&[0xa0, 0x51, 0xff]);
+        mock.expect(&[0x81, 0x01, 0x04, 0x00, 0x02, 0xff])
+            .reply(&[0x90, 0x41, 0xff])
+            .reply(&[0x90, 0x51, 0xff]);
+
+        let mut runner = ScriptRunner::new();
+        runner.add_camera("left", Camera::with_transport(mock));
+        runner.add_camera_on_bus("right", "left", 2).unwrap();
+
+        let script = Script::parse("power off; camera right { power off }; power on").unwrap();
+        runner.run(&script).unwrap();
+
+        match runner.add_camera_on_bus("other", "left", 8) {
+            Err(Error::InvalidAddress) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::discovery::{Candidate, ENQUIRY};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Answers discovery searches as if it were the camera described by a `Candidate`.
+pub struct DiscoveryResponder {
+    socket: UdpSocket,
+    response: Vec<u8>,
+}
+
+impl DiscoveryResponder {
+    /// Listens for searches on `addr`, usually `0.0.0.0:52380`.
+    pub fn bind<A: ToSocketAddrs>(addr: A, camera: Candidate) -> io::Result<Self> {
+        Ok(DiscoveryResponder {
+            socket: UdpSocket::bind(addr)?,
+            response: camera.to_bytes(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Answers searches until the socket fails.
+    pub fn serve(&self) -> io::Result<()> {
+        let mut buf = [0; 64];
+
+        loop {
+            let (n, peer) = self.socket.recv_from(&mut buf)?;
+            if &buf[..n] == ENQUIRY {
+                self.socket.send_to(&self.response, peer)?;
+            }
+        }
+    }
+}
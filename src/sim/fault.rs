@@ -0,0 +1,98 @@
+use crate::packet::ErrorKind;
+use std::time::Duration;
+
+/// Something that can go wrong while a simulated camera answers a request.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Fault {
+    /// Send the completion without acknowledging the command first.
+    DropAck,
+    /// Wait before sending the completion.
+    DelayCompletion(Duration),
+    /// Flip bits in the first reply so that it can't be parsed.
+    Corrupt,
+    /// Send replies one byte at a time, so that they arrive over several reads.
+    SplitFrames,
+    /// Reject the request with an error instead of executing it, e.g. `FullBuffer`.
+    Error(ErrorKind),
+    /// Send a network change notice ahead of the replies.
+    NetworkChange,
+    /// Ignore the request entirely.
+    Silence,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum When {
+    At(usize),
+    Every(usize),
+    From(usize),
+}
+
+impl When {
+    fn matches(self, request: usize) -> bool {
+        match self {
+            When::At(n) => request == n,
+            When::Every(period) => (request + 1).is_multiple_of(period),
+            When::From(n) => request >= n,
+        }
+    }
+}
+
+/// Decides which faults to inject for each request a simulated camera receives.
+/// Requests are counted from zero in the order they arrive.
+#[derive(Clone, Debug, Default)]
+pub struct FaultSchedule {
+    rules: Vec<(When, Fault)>,
+}
+
+impl FaultSchedule {
+    pub fn new() -> Self {
+        FaultSchedule::default()
+    }
+
+    /// Injects `fault` into the `n`th request only.
+    pub fn at(mut self, n: usize, fault: Fault) -> Self {
+        self.rules.push((When::At(n), fault));
+        self
+    }
+
+    /// Injects `fault` into every `period`th request, starting with request `period - 1`.
+    pub fn every(mut self, period: usize, fault: Fault) -> Self {
+        assert!(period > 0);
+        self.rules.push((When::Every(period), fault));
+        self
+    }
+
+    /// Injects `fault` into the `n`th request and every request after it.
+    pub fn from(mut self, n: usize, fault: Fault) -> Self {
+        self.rules.push((When::From(n), fault));
+        self
+    }
+
+    pub fn faults_for(&self, request: usize) -> Vec<Fault> {
+        self.rules
+            .iter()
+            .filter(|(when, _)| when.matches(request))
+            .map(|(_, fault)| *fault)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let schedule = FaultSchedule::new()
+            .at(1, Fault::Corrupt)
+            .every(3, Fault::DropAck)
+            .from(5, Fault::Silence);
+
+        assert_eq!(schedule.faults_for(0), vec![]);
+        assert_eq!(schedule.faults_for(1), vec![Fault::Corrupt]);
+        assert_eq!(schedule.faults_for(2), vec![Fault::DropAck]);
+        assert_eq!(schedule.faults_for(4), vec![]);
+        assert_eq!(schedule.faults_for(5), vec![Fault::DropAck, Fault::Silence]);
+        assert_eq!(schedule.faults_for(6), vec![Fault::Silence]);
+    }
+}
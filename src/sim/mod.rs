@@ -0,0 +1,775 @@
+use crate::commands::PanTiltValue;
+use crate::models::Model;
+use crate::motion::SpeedTable;
+use crate::packet::ErrorKind;
+use crate::protocol::{Command, Direction, Inquiry, InquiryReply, Version};
+use crate::status::{
+    CameraControlStatus, EnlargementStatus, ExposureMode, LensStatus, OtherStatus, WhiteBalance,
+};
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod discovery;
+mod fault;
+#[cfg(target_os = "linux")]
+mod pty;
+mod transport;
+
+pub use self::discovery::DiscoveryResponder;
+pub use self::fault::{Fault, FaultSchedule};
+#[cfg(target_os = "linux")]
+pub use self::pty::VirtualSerialPort;
+pub use self::transport::SimTransport;
+
+const COMMAND_SOCKET: u8 = 1;
+const BROADCAST: u8 = 0x88;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Motion {
+    Idle,
+    Drive(f64),
+    Seek { target: f64, speed: f64 },
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Axis {
+    pos: f64,
+    min: f64,
+    max: f64,
+    motion: Motion,
+}
+
+impl Axis {
+    fn new(min: f64, max: f64) -> Self {
+        Axis {
+            pos: 0.0,
+            min,
+            max,
+            motion: Motion::Idle,
+        }
+    }
+
+    fn seek(&mut self, target: f64, speed: f64) {
+        let target = target.max(self.min).min(self.max);
+        self.motion = Motion::Seek { target, speed };
+    }
+
+    fn advance(&mut self, secs: f64) {
+        match self.motion {
+            Motion::Idle => {}
+            Motion::Drive(velocity) => {
+                self.pos = (self.pos + velocity * secs).max(self.min).min(self.max);
+                if self.pos == self.min || self.pos == self.max {
+                    self.motion = Motion::Idle;
+                }
+            }
+            Motion::Seek { target, speed } => {
+                let step = speed * secs;
+                if (target - self.pos).abs() <= step {
+                    self.pos = target;
+                    self.motion = Motion::Idle;
+                } else {
+                    self.pos += step * (target - self.pos).signum();
+                }
+            }
+        }
+    }
+
+    fn is_moving(&self) -> bool {
+        self.motion != Motion::Idle
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Preset {
+    pan_tilt: PanTiltValue,
+    zoom: u16,
+}
+
+/// A simulated PTZ camera that answers VISCA requests the way real hardware would.
+///
+/// The simulator emulates one of the known `Model`s, reaching the positions and moving at
+/// the speeds that model does. The simulation doesn't keep time by itself: call `advance`
+/// to move the camera along whatever motion its last commands started.
+pub struct Simulator {
+    model: &'static Model,
+    speeds: SpeedTable,
+    address: u8,
+    version: Version,
+    power: bool,
+    pan: Axis,
+    tilt: Axis,
+    zoom: Axis,
+    focus: Axis,
+    auto_focus: bool,
+    presets: [Option<Preset>; 16],
+    preset_speed: u8,
+    faults: FaultSchedule,
+    requests: usize,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Simulator::new()
+    }
+}
+
+impl Simulator {
+    /// Creates a simulator that emulates an EVI-D70.
+    pub fn new() -> Self {
+        Simulator::with_model(&Model::EVI_D70)
+    }
+
+    pub fn with_model(model: &'static Model) -> Self {
+        let speeds = model.speed_table();
+        let preset_speed = speeds.pan().len() as u8;
+        let focus = Axis::new(model.focus_range.0 as f64, model.focus_range.1 as f64);
+
+        Simulator {
+            model,
+            speeds,
+            address: 1,
+            version: Version {
+                vendor_id: 0x0001,
+                model_id: model.model_ids[0],
+                rom_version: 0x0100,
+                max_socket: 2,
+            },
+            power: true,
+            pan: pan_axis(model),
+            tilt: tilt_axis(model),
+            zoom: zoom_axis(model),
+            focus: Axis {
+                pos: focus.min,
+                ..focus
+            },
+            auto_focus: false,
+            presets: [None; 16],
+            preset_speed,
+            faults: FaultSchedule::new(),
+            requests: 0,
+        }
+    }
+
+    pub fn model(&self) -> &'static Model {
+        self.model
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Sets the address the simulator answers to, between 1 and 7.
+    pub fn set_address(&mut self, address: u8) -> crate::Result<()> {
+        if !(1..=7).contains(&address) {
+            return Err(crate::Error::InvalidAddress);
+        }
+        self.address = address;
+        Ok(())
+    }
+
+    pub fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    /// Sets the faults injected while serving requests. Requests are counted from the
+    /// moment the schedule is set.
+    pub fn set_faults(&mut self, faults: FaultSchedule) {
+        self.faults = faults;
+        self.requests = 0;
+    }
+
+    pub fn pan_tilt(&self) -> PanTiltValue {
+        PanTiltValue {
+            pan: self.pan.pos.round() as i16,
+            tilt: self.tilt.pos.round() as i16,
+        }
+    }
+
+    /// Puts the camera at a position instantly, stopping any pan-tilt motion.
+    pub fn set_pan_tilt(&mut self, pos: PanTiltValue) {
+        self.pan = Axis {
+            pos: pos.pan as f64,
+            ..pan_axis(self.model)
+        };
+        self.tilt = Axis {
+            pos: pos.tilt as f64,
+            ..tilt_axis(self.model)
+        };
+    }
+
+    pub fn zoom(&self) -> u16 {
+        self.zoom.pos.round() as u16
+    }
+
+    /// Puts the lens at a zoom position instantly, stopping any zoom motion.
+    pub fn set_zoom(&mut self, pos: u16) {
+        self.zoom = Axis {
+            pos: pos as f64,
+            ..zoom_axis(self.model)
+        };
+    }
+
+    pub fn focus(&self) -> u16 {
+        self.focus.pos.round() as u16
+    }
+
+    pub fn power(&self) -> bool {
+        self.power
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.pan.is_moving()
+            || self.tilt.is_moving()
+            || self.zoom.is_moving()
+            || self.focus.is_moving()
+    }
+
+    /// Moves the camera along by `dt` worth of motion.
+    pub fn advance(&mut self, dt: Duration) {
+        let secs = dt.as_secs_f64();
+        self.pan.advance(secs);
+        self.tilt.advance(secs);
+        self.zoom.advance(secs);
+        self.focus.advance(secs);
+    }
+
+    /// Answers requests arriving on `stream` until it reaches end of file, advancing the
+    /// simulation in real time.
+    pub fn serve<S: Read + Write>(&mut self, stream: &mut S) -> io::Result<()> {
+        let mut buf = [0; 64];
+        let mut pending = Vec::new();
+        let mut last = Instant::now();
+
+        loop {
+            let n = match stream.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            let now = Instant::now();
+            self.advance(now - last);
+            last = now;
+
+            pending.extend_from_slice(&buf[..n]);
+            while let Some(pos) = pending.iter().position(|&b| b == 0xff) {
+                let request: Vec<u8> = pending.drain(..=pos).collect();
+                self.serve_request(stream, &request)?;
+            }
+        }
+    }
+
+    /// Answers a single request, injecting whatever faults are scheduled for it.
+    fn serve_request<S: Write>(&mut self, stream: &mut S, request: &[u8]) -> io::Result<()> {
+        let faults = self.faults.faults_for(self.requests);
+        self.requests += 1;
+
+        self.respond(stream, request, &faults)
+    }
+
+    fn respond<S: Write>(
+        &mut self,
+        stream: &mut S,
+        request: &[u8],
+        faults: &[Fault],
+    ) -> io::Result<()> {
+        if !self.accepts(request) || faults.contains(&Fault::Silence) {
+            return Ok(());
+        }
+
+        let error = faults.iter().find_map(|fault| match fault {
+            Fault::Error(kind) => Some(*kind),
+            _ => None,
+        });
+        let mut replies = match error {
+            Some(ErrorKind::FullBuffer) => vec![self.error(0, ErrorKind::FullBuffer)],
+            Some(kind) => vec![self.error(COMMAND_SOCKET, kind)],
+            None => self.handle(request),
+        };
+
+        if faults.contains(&Fault::NetworkChange) {
+            replies.insert(0, vec![self.reply_header(), 0x38, 0xff]);
+        }
+        if faults.contains(&Fault::DropAck) {
+            replies.retain(|reply| reply[1] & 0xf0 != 0x40);
+        }
+        if let (true, Some(first)) = (faults.contains(&Fault::Corrupt), replies.first_mut()) {
+            first[1] ^= 0x80;
+        }
+
+        let delay = faults.iter().find_map(|fault| match fault {
+            Fault::DelayCompletion(delay) => Some(*delay),
+            _ => None,
+        });
+
+        for reply in replies {
+            if let (Some(delay), 0x50) = (delay, reply[1] & 0xf0) {
+                thread::sleep(delay);
+            }
+
+            if faults.contains(&Fault::SplitFrames) {
+                for byte in reply {
+                    stream.write_all(&[byte])?;
+                    stream.flush()?;
+                    thread::sleep(Duration::from_millis(2));
+                }
+            } else {
+                stream.write_all(&reply)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn accepts(&self, request: &[u8]) -> bool {
+        match request {
+            [BROADCAST, 0x30, _, 0xff] => true,
+            [header, _, _, .., 0xff] => header & 0x0f == self.address,
+            _ => false,
+        }
+    }
+
+    /// Handles a complete request packet and returns the reply packets the camera sends
+    /// back, which may be none at all if the request was addressed to another camera.
+    pub fn handle(&mut self, request: &[u8]) -> Vec<Vec<u8>> {
+        if !self.accepts(request) {
+            return Vec::new();
+        }
+
+        // AddressSet takes the first address on the bus, and passes the next one along to
+        // the following camera, or back to the controller if this is the last one.
+        if let [BROADCAST, 0x30, address @ 1..=7, 0xff] = *request {
+            self.address = address;
+            return vec![vec![BROADCAST, 0x30, address + 1, 0xff]];
+        }
+
+        if let Some(cmd) = Command::decode(request) {
+            return match self.execute(cmd) {
+                Ok(()) => vec![self.ack(), self.completion(COMMAND_SOCKET, &[])],
+                Err(kind) => vec![self.error(COMMAND_SOCKET, kind)],
+            };
+        }
+
+        if let Some(inq) = Inquiry::decode(request) {
+            let payload = self.inquire(inq).to_payload();
+            return vec![self.completion(0, &payload)];
+        }
+
+        if request.len() > 16 {
+            vec![self.error(0, ErrorKind::MsgLength)]
+        } else {
+            vec![self.error(0, ErrorKind::Syntax)]
+        }
+    }
+
+    fn execute(&mut self, cmd: Command) -> std::result::Result<(), ErrorKind> {
+        if !self.power && cmd != Command::Power(true) {
+            return Err(ErrorKind::NotExecutable);
+        }
+
+        match cmd {
+            Command::PanTiltDrive {
+                direction,
+                pan_speed,
+                tilt_speed,
+            } => {
+                let (pan_dir, tilt_dir) = direction_signs(direction);
+                let pan_speed = self.pan_speed(pan_speed)?;
+                let tilt_speed = self.tilt_speed(tilt_speed)?;
+
+                self.pan.motion = drive(pan_dir * pan_speed);
+                self.tilt.motion = drive(tilt_dir * tilt_speed);
+            }
+            Command::PanTiltAbsolute {
+                pan_speed,
+                tilt_speed,
+                position,
+            } => {
+                self.pan
+                    .seek(position.pan as f64, self.pan_speed(pan_speed)?);
+                self.tilt
+                    .seek(position.tilt as f64, self.tilt_speed(tilt_speed)?);
+            }
+            Command::PanTiltRelative {
+                pan_speed,
+                tilt_speed,
+                position,
+            } => {
+                let pan = self.pan.pos + position.pan as f64;
+                let tilt = self.tilt.pos + position.tilt as f64;
+                self.pan.seek(pan, self.pan_speed(pan_speed)?);
+                self.tilt.seek(tilt, self.tilt_speed(tilt_speed)?);
+            }
+            Command::PanTiltHome => self.seek_max_speed(PanTiltValue { pan: 0, tilt: 0 }),
+            Command::ZoomDirect(val) => self.zoom.seek(val as f64, self.zoom_speed(7)),
+            Command::ZoomStop => self.zoom.motion = Motion::Idle,
+            Command::ZoomTele(speed) => self.zoom.motion = drive(self.zoom_speed(speed)),
+            Command::ZoomWide(speed) => self.zoom.motion = drive(-self.zoom_speed(speed)),
+            Command::FocusDirect(val) => self.focus.seek(val as f64, self.focus_speed(7)),
+            Command::FocusStop => self.focus.motion = Motion::Idle,
+            Command::FocusAuto(on) => self.auto_focus = on,
+            // There's nothing in the picture to focus on, so the lens stays where it is.
+            Command::FocusOnePush => {}
+            Command::FocusFar(speed) => self.focus.motion = drive(-self.focus_speed(speed)),
+            Command::FocusNear(speed) => self.focus.motion = drive(self.focus_speed(speed)),
+            Command::PresetReset(num) => self.presets[num as usize & 0x0f] = None,
+            Command::PresetSet(num) => {
+                self.presets[num as usize & 0x0f] = Some(Preset {
+                    pan_tilt: self.pan_tilt(),
+                    zoom: self.zoom(),
+                });
+            }
+            Command::PresetRecall(num) => {
+                let preset = self.presets[num as usize & 0x0f].unwrap_or(Preset {
+                    pan_tilt: PanTiltValue { pan: 0, tilt: 0 },
+                    zoom: 0,
+                });
+                let speed = self.preset_speed;
+                self.pan
+                    .seek(preset.pan_tilt.pan as f64, self.pan_speed(speed)?);
+                self.tilt.seek(
+                    preset.pan_tilt.tilt as f64,
+                    self.tilt_speed(speed.min(self.speeds.tilt().len() as u8))?,
+                );
+                self.zoom.seek(preset.zoom as f64, self.zoom_speed(7));
+            }
+            Command::PresetSpeed(speed) => {
+                self.pan_speed(speed)?;
+                self.preset_speed = speed;
+            }
+            Command::Power(on) => self.power = on,
+        }
+
+        Ok(())
+    }
+
+    fn inquire(&self, inq: Inquiry) -> InquiryReply {
+        match inq {
+            Inquiry::PanTiltPosition => InquiryReply::PanTiltPosition(self.pan_tilt()),
+            Inquiry::ZoomPosition => InquiryReply::ZoomPosition(self.zoom()),
+            Inquiry::FocusPosition => InquiryReply::FocusPosition(self.focus()),
+            Inquiry::Power => InquiryReply::Power(self.power),
+            Inquiry::Version => InquiryReply::Version(self.version),
+            Inquiry::LensBlock => InquiryReply::LensBlock(LensStatus {
+                zoom: self.zoom(),
+                focus_near_limit: 0x10,
+                focus: self.focus(),
+                auto_focus: self.auto_focus,
+                digital_zoom: false,
+                zooming: self.zoom.is_moving(),
+                focusing: self.focus.is_moving(),
+            }),
+            // The simulator has no exposure controls, so it reports a camera left on auto.
+            Inquiry::CameraBlock => InquiryReply::CameraBlock(CameraControlStatus {
+                red_gain: 0,
+                blue_gain: 0,
+                white_balance: WhiteBalance::Auto,
+                aperture: 0,
+                exposure: ExposureMode::FullAuto,
+                backlight: false,
+                exposure_compensation: false,
+                shutter: 0,
+                iris: 0,
+                gain: 0,
+                bright: 0,
+                exposure_compensation_level: 0,
+            }),
+            Inquiry::OtherBlock => InquiryReply::OtherBlock(OtherStatus {
+                power: self.power,
+                mirror: false,
+                freeze: false,
+                flip: false,
+                picture_effect: 0,
+            }),
+            Inquiry::EnlargementBlock => InquiryReply::EnlargementBlock(EnlargementStatus {
+                digital_zoom: 0,
+                af_activation_time: 0,
+                af_interval_time: 0,
+            }),
+        }
+    }
+
+    fn seek_max_speed(&mut self, target: PanTiltValue) {
+        let pan_speed = self.speeds.pan()[self.speeds.pan().len() - 1];
+        let tilt_speed = self.speeds.tilt()[self.speeds.tilt().len() - 1];
+        self.pan.seek(target.pan as f64, pan_speed);
+        self.tilt.seek(target.tilt as f64, tilt_speed);
+    }
+
+    fn pan_speed(&self, speed: u8) -> std::result::Result<f64, ErrorKind> {
+        speed_units(speed, self.speeds.pan())
+    }
+
+    fn tilt_speed(&self, speed: u8) -> std::result::Result<f64, ErrorKind> {
+        speed_units(speed, self.speeds.tilt())
+    }
+
+    /// Variable lens speeds above 7 run at the top speed.
+    fn zoom_speed(&self, speed: u8) -> f64 {
+        self.speeds.zoom()[speed.min(7) as usize]
+    }
+
+    fn focus_speed(&self, speed: u8) -> f64 {
+        self.speeds.focus()[speed.min(7) as usize]
+    }
+
+    fn reply_header(&self) -> u8 {
+        (self.address + 8) << 4
+    }
+
+    fn ack(&self) -> Vec<u8> {
+        vec![self.reply_header(), 0x40 | COMMAND_SOCKET, 0xff]
+    }
+
+    fn completion(&self, socket: u8, payload: &[u8]) -> Vec<u8> {
+        let mut reply = vec![self.reply_header(), 0x50 | socket];
+        reply.extend_from_slice(payload);
+        reply.push(0xff);
+        reply
+    }
+
+    fn error(&self, socket: u8, kind: ErrorKind) -> Vec<u8> {
+        vec![self.reply_header(), 0x60 | socket, kind as u8, 0xff]
+    }
+}
+
+fn drive(velocity: f64) -> Motion {
+    if velocity == 0.0 {
+        Motion::Idle
+    } else {
+        Motion::Drive(velocity)
+    }
+}
+
+fn direction_signs(direction: Direction) -> (f64, f64) {
+    match direction {
+        Direction::Up => (0.0, 1.0),
+        Direction::Down => (0.0, -1.0),
+        Direction::Left => (-1.0, 0.0),
+        Direction::Right => (1.0, 0.0),
+        Direction::UpLeft => (-1.0, 1.0),
+        Direction::UpRight => (1.0, 1.0),
+        Direction::DownLeft => (-1.0, -1.0),
+        Direction::DownRight => (1.0, -1.0),
+        Direction::Stop => (0.0, 0.0),
+    }
+}
+
+fn speed_units(speed: u8, speeds: &[f64]) -> std::result::Result<f64, ErrorKind> {
+    match speed.checked_sub(1).and_then(|i| speeds.get(i as usize)) {
+        Some(&units) => Ok(units),
+        None => Err(ErrorKind::Syntax),
+    }
+}
+
+fn pan_axis(model: &Model) -> Axis {
+    let cal = &model.calibration;
+    let center = cal.center.pan as f64;
+    Axis::new(
+        (center + cal.pan_min * cal.pan_units_per_degree).round(),
+        (center + cal.pan_max * cal.pan_units_per_degree).round(),
+    )
+}
+
+fn tilt_axis(model: &Model) -> Axis {
+    let cal = &model.calibration;
+    let center = cal.center.tilt as f64;
+    Axis::new(
+        (center + cal.tilt_min * cal.tilt_units_per_degree).round(),
+        (center + cal.tilt_max * cal.tilt_units_per_degree).round(),
+    )
+}
+
+fn zoom_axis(model: &Model) -> Axis {
+    let points = model.zoom_points;
+    Axis::new(points[0].0 as f64, points[points.len() - 1].0 as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(sim: &mut Simulator, cmd: Command) -> Vec<Vec<u8>> {
+        sim.handle(cmd.encode(1).unwrap().as_bytes())
+    }
+
+    #[test]
+    fn test_command_replies() {
+        let mut sim = Simulator::new();
+        let replies = send(&mut sim, Command::ZoomDirect(0x2000));
+
+        assert_eq!(
+            replies,
+            vec![vec![0x90, 0x41, 0xff], vec![0x90, 0x51, 0xff]]
+        );
+    }
+
+    #[test]
+    fn test_inquiry_reply() {
+        let mut sim = Simulator::new();
+        let replies = sim.handle(&[0x81, 0x09, 0x04, 0x00, 0xff]);
+
+        assert_eq!(replies, vec![vec![0x90, 0x50, 0x02, 0xff]]);
+    }
+
+    #[test]
+    fn test_unknown_request() {
+        let mut sim = Simulator::new();
+        let replies = sim.handle(&[0x81, 0x01, 0x04, 0x10, 0x02, 0xff]);
+
+        assert_eq!(replies, vec![vec![0x90, 0x60, 0x02, 0xff]]);
+    }
+
+    #[test]
+    fn test_address_set() {
+        let mut sim = Simulator::new();
+        assert_eq!(
+            sim.handle(&[0x88, 0x30, 0x03, 0xff]),
+            vec![vec![0x88, 0x30, 0x04, 0xff]]
+        );
+        assert_eq!(sim.address(), 3);
+    }
+
+    #[test]
+    fn test_set_address() {
+        let mut sim = Simulator::new();
+        sim.set_address(7).unwrap();
+        assert_eq!(sim.address(), 7);
+
+        for address in [0, 8, 0xff].iter() {
+            match sim.set_address(*address) {
+                Err(crate::Error::InvalidAddress) => {}
+                other => panic!("expected InvalidAddress for {}, got {:?}", address, other),
+            }
+        }
+        assert_eq!(sim.address(), 7);
+    }
+
+    #[test]
+    fn test_other_address_ignored() {
+        let mut sim = Simulator::new();
+        assert!(sim.handle(&[0x82, 0x09, 0x04, 0x00, 0xff]).is_empty());
+    }
+
+    #[test]
+    fn test_absolute_move() {
+        let mut sim = Simulator::new();
+        let position = PanTiltValue {
+            pan: 500,
+            tilt: -100,
+        };
+        send(
+            &mut sim,
+            Command::PanTiltAbsolute {
+                pan_speed: 0x0a,
+                tilt_speed: 0x01,
+                position,
+            },
+        );
+
+        sim.advance(Duration::from_millis(500));
+        assert_eq!(
+            sim.pan_tilt(),
+            PanTiltValue {
+                pan: 278,
+                tilt: -26
+            }
+        );
+        assert!(sim.is_moving());
+
+        sim.advance(Duration::from_secs(2));
+        assert_eq!(sim.pan_tilt(), position);
+        assert!(!sim.is_moving());
+    }
+
+    #[test]
+    fn test_drive_until_stopped() {
+        let mut sim = Simulator::new();
+        send(
+            &mut sim,
+            Command::PanTiltDrive {
+                direction: Direction::Left,
+                pan_speed: 0x02,
+                tilt_speed: 0x01,
+            },
+        );
+
+        sim.advance(Duration::from_secs(1));
+        send(
+            &mut sim,
+            Command::PanTiltDrive {
+                direction: Direction::Stop,
+                pan_speed: 0x02,
+                tilt_speed: 0x01,
+            },
+        );
+        sim.advance(Duration::from_secs(1));
+
+        assert_eq!(sim.pan_tilt(), PanTiltValue { pan: -111, tilt: 0 });
+    }
+
+    #[test]
+    fn test_lens_drive() {
+        let mut sim = Simulator::new();
+        send(&mut sim, Command::ZoomTele(7));
+        sim.advance(Duration::from_millis(1500));
+        send(&mut sim, Command::ZoomWide(3));
+        sim.advance(Duration::from_millis(1500));
+        send(&mut sim, Command::ZoomStop);
+        sim.advance(Duration::from_secs(1));
+        assert_eq!(sim.zoom(), 0x1000);
+
+        send(&mut sim, Command::FocusNear(7));
+        sim.advance(Duration::from_millis(500));
+        send(&mut sim, Command::FocusFar(7));
+        sim.advance(Duration::from_secs(1));
+        assert_eq!(sim.focus(), 0x1000);
+        assert!(!sim.is_moving());
+    }
+
+    #[test]
+    fn test_preset_speed() {
+        let mut sim = Simulator::new();
+        sim.set_pan_tilt(PanTiltValue { pan: 500, tilt: 0 });
+        send(&mut sim, Command::PresetSet(1));
+        sim.set_pan_tilt(PanTiltValue { pan: 0, tilt: 0 });
+
+        send(&mut sim, Command::PresetSpeed(0x02));
+        send(&mut sim, Command::PresetRecall(1));
+        sim.advance(Duration::from_secs(1));
+        assert_eq!(sim.pan_tilt(), PanTiltValue { pan: 111, tilt: 0 });
+
+        assert_eq!(
+            send(&mut sim, Command::PresetSpeed(0x20)),
+            vec![vec![0x90, 0x61, 0x02, 0xff]]
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let mut sim = Simulator::new();
+        send(&mut sim, Command::ZoomDirect(0x1000));
+        sim.advance(Duration::from_secs(3));
+        send(&mut sim, Command::PresetSet(2));
+
+        send(&mut sim, Command::ZoomDirect(0));
+        sim.advance(Duration::from_secs(3));
+        assert_eq!(sim.zoom(), 0);
+
+        send(&mut sim, Command::PresetRecall(2));
+        sim.advance(Duration::from_secs(3));
+        assert_eq!(sim.zoom(), 0x1000);
+    }
+
+    #[test]
+    fn test_power_off() {
+        let mut sim = Simulator::new();
+        send(&mut sim, Command::Power(false));
+
+        let replies = send(&mut sim, Command::PanTiltHome);
+        assert_eq!(replies, vec![vec![0x90, 0x61, 0x41, 0xff]]);
+    }
+}
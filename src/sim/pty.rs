@@ -0,0 +1,78 @@
+use super::Simulator;
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+
+/// A Linux pseudo-terminal whose far end behaves like a serial port with a camera attached.
+pub struct VirtualSerialPort {
+    master: File,
+    // Holding the slave side open keeps the master readable while no client is connected.
+    _slave: File,
+    path: PathBuf,
+}
+
+impl VirtualSerialPort {
+    pub fn open() -> io::Result<Self> {
+        let master = unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            File::from_raw_fd(fd)
+        };
+
+        let path = unsafe {
+            let fd = master.as_raw_fd();
+            if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut buf = [0 as libc::c_char; 128];
+            if libc::ptsname_r(fd, buf.as_mut_ptr(), buf.len()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            PathBuf::from(CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned())
+        };
+
+        let slave = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(&path)?;
+        make_raw(&slave)?;
+
+        Ok(VirtualSerialPort {
+            master,
+            _slave: slave,
+            path,
+        })
+    }
+
+    /// The device path that clients should open, e.g. `/dev/pts/3`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Answers requests arriving on the port with `sim` until an I/O error occurs.
+    pub fn serve(&mut self, sim: &mut Simulator) -> io::Result<()> {
+        let mut master = &self.master;
+        sim.serve(&mut master)
+    }
+}
+
+fn make_raw(file: &File) -> io::Result<()> {
+    unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        if libc::tcgetattr(file.as_raw_fd(), &mut termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        libc::cfmakeraw(&mut termios);
+        if libc::tcsetattr(file.as_raw_fd(), libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
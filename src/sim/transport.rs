@@ -0,0 +1,85 @@
+use super::Simulator;
+use crate::transport::Transport;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Connects a `Camera` straight to a `Simulator` in the same process, advancing the
+/// simulation in real time.
+///
+/// Replies are available as soon as a request has been sent, so a request the simulator
+/// doesn't answer times out immediately rather than after the usual second.
+pub struct SimTransport {
+    sim: Arc<Mutex<Simulator>>,
+    last: Instant,
+    replies: Vec<u8>,
+}
+
+impl SimTransport {
+    pub fn new(sim: Simulator) -> Self {
+        SimTransport {
+            sim: Arc::new(Mutex::new(sim)),
+            last: Instant::now(),
+            replies: Vec::new(),
+        }
+    }
+
+    /// Returns the simulator, so that its state can be inspected while it is in use.
+    pub fn simulator(&self) -> Arc<Mutex<Simulator>> {
+        self.sim.clone()
+    }
+}
+
+impl Transport for SimTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        let mut sim = self.sim.lock().unwrap();
+
+        let now = Instant::now();
+        sim.advance(now - self.last);
+        self.last = now;
+
+        sim.serve_request(&mut self.replies, packet)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.replies.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "no reply"));
+        }
+
+        let len = self.replies.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.replies[..len]);
+        self.replies.drain(..len);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{Fault, FaultSchedule};
+    use crate::{Camera, Error};
+
+    #[test]
+    fn test_camera_over_sim_transport() {
+        let transport = SimTransport::new(Simulator::new());
+        let sim = transport.simulator();
+        let mut camera = Camera::with_transport(transport);
+
+        camera.focus().set(0x8000).unwrap();
+        assert!(sim.lock().unwrap().is_moving());
+        assert!(camera.focus().get().unwrap() < 0x8000);
+    }
+
+    #[test]
+    fn test_silence_times_out() {
+        let mut sim = Simulator::new();
+        sim.set_faults(FaultSchedule::new().at(0, Fault::Silence));
+        let mut camera = Camera::with_transport(SimTransport::new(sim));
+
+        match camera.power().get() {
+            Err(Error::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(camera.power().get().unwrap());
+    }
+}
@@ -0,0 +1,338 @@
+//! Status blocks returned by Sony's block inquiries.
+//!
+//! Each block inquiry, `8x 09 7E 7E 0n FF`, is answered by a completion carrying 13 bytes
+//! of payload that cover a whole group of settings. The layouts below are the ones used
+//! by Sony's EVI and FCB cameras; bytes they reserve are sent as zero.
+
+use crate::protocol::{u16_from_nibbles, u16_to_nibbles};
+
+pub(crate) const BLOCK_LEN: usize = 13;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WhiteBalance {
+    Auto,
+    Indoor,
+    Outdoor,
+    OnePush,
+    Atw,
+    Manual,
+    /// A model-specific mode.
+    Other(u8),
+}
+
+impl WhiteBalance {
+    fn from_u8(b: u8) -> Self {
+        match b {
+            0x0 => WhiteBalance::Auto,
+            0x1 => WhiteBalance::Indoor,
+            0x2 => WhiteBalance::Outdoor,
+            0x3 => WhiteBalance::OnePush,
+            0x4 => WhiteBalance::Atw,
+            0x5 => WhiteBalance::Manual,
+            b => WhiteBalance::Other(b),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            WhiteBalance::Auto => 0x0,
+            WhiteBalance::Indoor => 0x1,
+            WhiteBalance::Outdoor => 0x2,
+            WhiteBalance::OnePush => 0x3,
+            WhiteBalance::Atw => 0x4,
+            WhiteBalance::Manual => 0x5,
+            WhiteBalance::Other(b) => b,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExposureMode {
+    FullAuto,
+    Manual,
+    ShutterPriority,
+    IrisPriority,
+    Bright,
+    /// A model-specific mode.
+    Other(u8),
+}
+
+impl ExposureMode {
+    fn from_u8(b: u8) -> Self {
+        match b {
+            0x0 => ExposureMode::FullAuto,
+            0x3 => ExposureMode::Manual,
+            0xa => ExposureMode::ShutterPriority,
+            0xb => ExposureMode::IrisPriority,
+            0xd => ExposureMode::Bright,
+            b => ExposureMode::Other(b),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            ExposureMode::FullAuto => 0x0,
+            ExposureMode::Manual => 0x3,
+            ExposureMode::ShutterPriority => 0xa,
+            ExposureMode::IrisPriority => 0xb,
+            ExposureMode::Bright => 0xd,
+            ExposureMode::Other(b) => b,
+        }
+    }
+}
+
+/// The lens control block, `8x 09 7E 7E 00 FF`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LensStatus {
+    pub zoom: u16,
+    /// The high byte of the closest position the focus is allowed to reach.
+    pub focus_near_limit: u8,
+    pub focus: u16,
+    pub auto_focus: bool,
+    pub digital_zoom: bool,
+    /// Whether a zoom command is still being executed.
+    pub zooming: bool,
+    /// Whether a focus command is still being executed.
+    pub focusing: bool,
+}
+
+impl LensStatus {
+    pub(crate) fn from_payload(p: &[u8]) -> Option<Self> {
+        if p.len() != BLOCK_LEN {
+            return None;
+        }
+
+        Some(LensStatus {
+            zoom: u16_from_nibbles(&p[0..4]),
+            focus_near_limit: byte_from_nibbles(p[4], p[5]),
+            focus: u16_from_nibbles(&p[6..10]),
+            auto_focus: bit(p[11], 0),
+            digital_zoom: bit(p[11], 2),
+            zooming: bit(p[12], 0),
+            focusing: bit(p[12], 1),
+        })
+    }
+
+    pub(crate) fn to_payload(self) -> [u8; BLOCK_LEN] {
+        let mut p = [0; BLOCK_LEN];
+        p[0..4].copy_from_slice(&u16_to_nibbles(self.zoom));
+        p[4..6].copy_from_slice(&byte_to_nibbles(self.focus_near_limit));
+        p[6..10].copy_from_slice(&u16_to_nibbles(self.focus));
+        p[11] = bits(&[self.auto_focus, false, self.digital_zoom]);
+        p[12] = bits(&[self.zooming, self.focusing]);
+        p
+    }
+}
+
+/// The camera control block, `8x 09 7E 7E 01 FF`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CameraControlStatus {
+    pub red_gain: u8,
+    pub blue_gain: u8,
+    pub white_balance: WhiteBalance,
+    pub aperture: u8,
+    pub exposure: ExposureMode,
+    pub backlight: bool,
+    pub exposure_compensation: bool,
+    pub shutter: u8,
+    pub iris: u8,
+    pub gain: u8,
+    pub bright: u8,
+    pub exposure_compensation_level: u8,
+}
+
+impl CameraControlStatus {
+    pub(crate) fn from_payload(p: &[u8]) -> Option<Self> {
+        if p.len() != BLOCK_LEN {
+            return None;
+        }
+
+        Some(CameraControlStatus {
+            red_gain: byte_from_nibbles(p[0], p[1]),
+            blue_gain: byte_from_nibbles(p[2], p[3]),
+            white_balance: WhiteBalance::from_u8(p[4]),
+            aperture: p[5],
+            exposure: ExposureMode::from_u8(p[6]),
+            exposure_compensation: bit(p[7], 1),
+            backlight: bit(p[7], 2),
+            shutter: p[8],
+            iris: p[9],
+            gain: p[10],
+            bright: p[11],
+            exposure_compensation_level: p[12],
+        })
+    }
+
+    pub(crate) fn to_payload(self) -> [u8; BLOCK_LEN] {
+        let mut p = [0; BLOCK_LEN];
+        p[0..2].copy_from_slice(&byte_to_nibbles(self.red_gain));
+        p[2..4].copy_from_slice(&byte_to_nibbles(self.blue_gain));
+        p[4] = self.white_balance.to_u8();
+        p[5] = self.aperture;
+        p[6] = self.exposure.to_u8();
+        p[7] = bits(&[false, self.exposure_compensation, self.backlight]);
+        p[8] = self.shutter;
+        p[9] = self.iris;
+        p[10] = self.gain;
+        p[11] = self.bright;
+        p[12] = self.exposure_compensation_level;
+        p
+    }
+}
+
+/// The block of other settings, `8x 09 7E 7E 02 FF`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OtherStatus {
+    pub power: bool,
+    /// Whether the picture is reversed left to right.
+    pub mirror: bool,
+    pub freeze: bool,
+    pub flip: bool,
+    pub picture_effect: u8,
+}
+
+impl OtherStatus {
+    pub(crate) fn from_payload(p: &[u8]) -> Option<Self> {
+        if p.len() != BLOCK_LEN {
+            return None;
+        }
+
+        Some(OtherStatus {
+            power: bit(p[0], 0),
+            mirror: bit(p[1], 0),
+            freeze: bit(p[1], 1),
+            flip: bit(p[1], 2),
+            picture_effect: p[2],
+        })
+    }
+
+    pub(crate) fn to_payload(self) -> [u8; BLOCK_LEN] {
+        let mut p = [0; BLOCK_LEN];
+        p[0] = bits(&[self.power]);
+        p[1] = bits(&[self.mirror, self.freeze, self.flip]);
+        p[2] = self.picture_effect;
+        p
+    }
+}
+
+/// The enlargement function block, `8x 09 7E 7E 03 FF`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EnlargementStatus {
+    pub digital_zoom: u16,
+    /// How long autofocus stays active in interval mode.
+    pub af_activation_time: u8,
+    /// How long autofocus waits between activations in interval mode.
+    pub af_interval_time: u8,
+}
+
+impl EnlargementStatus {
+    pub(crate) fn from_payload(p: &[u8]) -> Option<Self> {
+        if p.len() != BLOCK_LEN {
+            return None;
+        }
+
+        Some(EnlargementStatus {
+            digital_zoom: u16_from_nibbles(&p[0..4]),
+            af_activation_time: byte_from_nibbles(p[4], p[5]),
+            af_interval_time: byte_from_nibbles(p[6], p[7]),
+        })
+    }
+
+    pub(crate) fn to_payload(self) -> [u8; BLOCK_LEN] {
+        let mut p = [0; BLOCK_LEN];
+        p[0..4].copy_from_slice(&u16_to_nibbles(self.digital_zoom));
+        p[4..6].copy_from_slice(&byte_to_nibbles(self.af_activation_time));
+        p[6..8].copy_from_slice(&byte_to_nibbles(self.af_interval_time));
+        p
+    }
+}
+
+/// Everything the four block inquiries report about a camera.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CameraStatus {
+    pub lens: LensStatus,
+    pub camera: CameraControlStatus,
+    pub other: OtherStatus,
+    pub enlargement: EnlargementStatus,
+}
+
+fn byte_from_nibbles(hi: u8, lo: u8) -> u8 {
+    (hi & 0x0f) << 4 | (lo & 0x0f)
+}
+
+fn byte_to_nibbles(b: u8) -> [u8; 2] {
+    [b >> 4, b & 0x0f]
+}
+
+fn bit(b: u8, n: u8) -> bool {
+    b & (1 << n) != 0
+}
+
+fn bits(flags: &[bool]) -> u8 {
+    flags
+        .iter()
+        .enumerate()
+        .fold(0, |b, (n, &set)| if set { b | 1 << n } else { b })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lens_block() {
+        let payload = [
+            0x01, 0x02, 0x03, 0x04, 0x01, 0x00, 0x0a, 0x0b, 0x0c, 0x0d, 0x00, 0x05, 0x01,
+        ];
+        let lens = LensStatus::from_payload(&payload).unwrap();
+
+        assert_eq!(
+            lens,
+            LensStatus {
+                zoom: 0x1234,
+                focus_near_limit: 0x10,
+                focus: 0xabcd,
+                auto_focus: true,
+                digital_zoom: true,
+                zooming: true,
+                focusing: false,
+            }
+        );
+        assert_eq!(lens.to_payload(), payload);
+    }
+
+    #[test]
+    fn test_camera_control_block() {
+        let payload = [
+            0x0e, 0x01, 0x0c, 0x00, 0x03, 0x05, 0x0b, 0x04, 0x11, 0x0d, 0x02, 0x0f, 0x07,
+        ];
+        let camera = CameraControlStatus::from_payload(&payload).unwrap();
+
+        assert_eq!(camera.red_gain, 0xe1);
+        assert_eq!(camera.blue_gain, 0xc0);
+        assert_eq!(camera.white_balance, WhiteBalance::OnePush);
+        assert_eq!(camera.exposure, ExposureMode::IrisPriority);
+        assert!(camera.backlight);
+        assert!(!camera.exposure_compensation);
+        assert_eq!(camera.shutter, 0x11);
+        assert_eq!(camera.iris, 0x0d);
+        assert_eq!(camera.to_payload(), payload);
+    }
+
+    #[test]
+    fn test_model_specific_modes() {
+        let mut payload = [0; BLOCK_LEN];
+        payload[4] = 0x06;
+        payload[6] = 0x1f;
+        let camera = CameraControlStatus::from_payload(&payload).unwrap();
+
+        assert_eq!(camera.white_balance, WhiteBalance::Other(0x06));
+        assert_eq!(camera.exposure, ExposureMode::Other(0x1f));
+        assert_eq!(camera.to_payload(), payload);
+    }
+
+    #[test]
+    fn test_short_block() {
+        assert_eq!(OtherStatus::from_payload(&[0x01; 12]), None);
+    }
+}
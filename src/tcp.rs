@@ -0,0 +1,174 @@
+use crate::transport::Transport;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// The port most cameras accept raw VISCA over TCP on.
+pub const VISCA_TCP_PORT: u16 = 5678;
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Speaks plain VISCA, without any IP header, over a TCP connection.
+///
+/// If the camera drops the connection between requests, the next request is sent over a
+/// new connection. A request whose reply is cut off by the drop fails with an I/O error.
+pub struct TcpTransport {
+    addrs: Vec<SocketAddr>,
+    timeout: Duration,
+    stream: Option<TcpStream>,
+}
+
+impl TcpTransport {
+    pub fn connect<A: ToSocketAddrs>(addr: A, timeout: Duration) -> io::Result<Self> {
+        let mut transport = TcpTransport {
+            addrs: addr.to_socket_addrs()?.collect(),
+            timeout,
+            stream: None,
+        };
+
+        transport.reconnect()?;
+        Ok(transport)
+    }
+
+    fn reconnect(&mut self) -> io::Result<&mut TcpStream> {
+        self.stream = None;
+
+        let mut last_err = io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to");
+        for addr in &self.addrs {
+            match TcpStream::connect_timeout(addr, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+                    stream.set_nodelay(true)?;
+                    return Ok(self.stream.insert(stream));
+                }
+                Err(err) => last_err = err,
+            }
+        }
+
+        Err(last_err)
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        let stream = match self.stream {
+            Some(ref mut stream) if !is_closed(stream) => stream,
+            _ => self.reconnect()?,
+        };
+
+        match stream.write_all(packet) {
+            // The camera may have closed an idle connection since the last request.
+            Err(ref err) if is_disconnect(err) => self.reconnect()?.write_all(packet),
+            result => result,
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+
+        match stream.read(buf) {
+            Ok(0) => {
+                self.stream = None;
+                Err(io::ErrorKind::ConnectionAborted.into())
+            }
+            Err(err) if is_disconnect(&err) => {
+                self.stream = None;
+                Err(err)
+            }
+            result => result,
+        }
+    }
+}
+
+/// Checks whether the camera has hung up since the last reply, without blocking.
+fn is_closed(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+
+    let closed = match stream.peek(&mut [0]) {
+        Ok(n) => n == 0,
+        Err(err) => err.kind() != io::ErrorKind::WouldBlock,
+    };
+
+    stream.set_nonblocking(false).is_err() || closed
+}
+
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Camera, Error};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Accepts `conns` connections in turn, answering one request on each with `reply`
+    /// before hanging up.
+    fn serve(conns: usize, reply: &'static [u8]) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(conns) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 16];
+                let n = stream.read(&mut buf).unwrap();
+                assert_eq!(buf[n - 1], 0xff);
+
+                // Split the reply to check that the framing copes with partial reads.
+                let (head, tail) = reply.split_at(2);
+                stream.write_all(head).unwrap();
+                stream.flush().unwrap();
+                thread::sleep(Duration::from_millis(10));
+                stream.write_all(tail).unwrap();
+            }
+        });
+
+        addr
+    }
+
+    #[test]
+    fn test_framing() {
+        let addr = serve(1, &[0x90, 0x50, 0x01, 0x02, 0x03, 0x04, 0xff]);
+
+        let mut camera = Camera::connect_tcp(addr).unwrap();
+        assert_eq!(camera.zoom().get().unwrap(), 0x1234);
+    }
+
+    #[test]
+    fn test_reconnect_after_drop() {
+        let addr = serve(2, &[0x90, 0x50, 0x02, 0xff]);
+
+        let mut camera = Camera::connect_tcp(addr).unwrap();
+        assert!(camera.power().get().unwrap());
+
+        // The server hangs up after the first reply, so the next request reconnects.
+        thread::sleep(Duration::from_millis(50));
+        assert!(camera.power().get().unwrap());
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        match Camera::connect_tcp(addr) {
+            Err(Error::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
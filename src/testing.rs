@@ -0,0 +1,32 @@
+//! Fixtures shared by the unit tests.
+
+use crate::sim::{SimTransport, Simulator};
+use crate::Camera;
+use std::sync::{Arc, Mutex};
+
+/// Returns a camera answered by a fresh simulator.
+pub fn camera() -> Camera {
+    camera_with(Simulator::new())
+}
+
+/// Returns a camera answered by `sim`.
+pub fn camera_with(sim: Simulator) -> Camera {
+    Camera::with_transport(SimTransport::new(sim))
+}
+
+/// Returns a camera answered by a fresh simulator, along with the simulator so that tests
+/// can look at where it has moved to.
+pub fn sim_camera() -> (Camera, Arc<Mutex<Simulator>>) {
+    sim_camera_with(Simulator::new())
+}
+
+/// Returns a camera answered by `sim`, along with the simulator.
+pub fn sim_camera_with(sim: Simulator) -> (Camera, Arc<Mutex<Simulator>>) {
+    let transport = SimTransport::new(sim);
+    let sim = transport.simulator();
+    (Camera::with_transport(transport), sim)
+}
+
+pub fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+}
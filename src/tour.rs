@@ -0,0 +1,411 @@
+use crate::action::{perform, Action};
+use crate::commands::PanTiltValue;
+use crate::{Camera, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Somewhere for the camera to point during a tour.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Shot {
+    /// A preset stored in the camera, recalled at `speed` if one is given.
+    Preset { num: u8, speed: Option<u8> },
+    /// A position kept on the host, moved to at the given pan and tilt speeds.
+    Position {
+        pan_tilt: PanTiltValue,
+        zoom: u16,
+        pan_speed: u8,
+        tilt_speed: u8,
+    },
+}
+
+/// A shot and how long to stay on it once it has been recalled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TourStop {
+    pub shot: Shot,
+    pub dwell: Duration,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TourOrder {
+    /// Visits the stops in the order they were given.
+    Sequential,
+    /// Visits every stop once per round, in a different random order each time.
+    Shuffled,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Control {
+    Pause,
+    Resume,
+    Skip,
+    Stop,
+}
+
+struct Shared {
+    camera: Mutex<Camera>,
+    current: Mutex<Option<usize>>,
+    /// Set once someone takes the camera over, so that the tour sends nothing more.
+    taken_over: AtomicBool,
+    /// Why the camera couldn't be sent to the last stop, if it couldn't.
+    error: Mutex<Option<String>>,
+    paused: AtomicBool,
+    running: AtomicBool,
+}
+
+/// Cycles a camera through a list of shots in the background.
+///
+/// The tour owns the camera while it runs. Taking the camera back with `camera()` ends
+/// the tour, so that a manual command is never undone by the next stop. Only that ends
+/// it, though: the tour can't see commands that other controllers send to the camera,
+/// or ones sent through a `Monitor` watching it, and moves on to its next stop regardless.
+///
+/// A stop the camera can't be sent to is passed over, and the error is kept for `error()`
+/// until a later stop succeeds.
+pub struct Tour {
+    shared: Arc<Shared>,
+    control: Sender<Control>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Tour {
+    pub fn start(camera: Camera, stops: Vec<TourStop>, order: TourOrder) -> Self {
+        let shared = Arc::new(Shared {
+            camera: Mutex::new(camera),
+            current: Mutex::new(None),
+            taken_over: AtomicBool::new(false),
+            error: Mutex::new(None),
+            paused: AtomicBool::new(false),
+            running: AtomicBool::new(true),
+        });
+        let (control, received) = mpsc::channel();
+
+        let runner = shared.clone();
+        let thread = thread::spawn(move || {
+            runner.run(&stops, order, received);
+            runner.running.store(false, Ordering::SeqCst);
+        });
+
+        Tour {
+            shared,
+            control,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stays on the current stop until `resume` is called.
+    pub fn pause(&self) {
+        self.shared.paused.store(true, Ordering::SeqCst);
+        let _ = self.control.send(Control::Pause);
+    }
+
+    /// Carries on with whatever was left of the current stop's dwell time.
+    pub fn resume(&self) {
+        self.shared.paused.store(false, Ordering::SeqCst);
+        let _ = self.control.send(Control::Resume);
+    }
+
+    /// Moves on to the next stop straight away. A paused tour stays paused there.
+    pub fn skip(&self) {
+        let _ = self.control.send(Control::Skip);
+    }
+
+    /// Returns the index of the stop the camera was last sent to.
+    pub fn current(&self) -> Option<usize> {
+        *self.shared.current.lock().unwrap()
+    }
+
+    /// Returns why the camera couldn't be sent to the last stop, or `None` if it was.
+    pub fn error(&self) -> Option<String> {
+        self.shared.error.lock().unwrap().clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the tour is still going. It ends when the camera is taken over or
+    /// there are no stops.
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::SeqCst)
+    }
+
+    /// Ends the tour and gives access to the camera for manual control. The camera is
+    /// handed over as soon as any command the tour is in the middle of completes.
+    pub fn camera(&self) -> MutexGuard<'_, Camera> {
+        self.shared.taken_over.store(true, Ordering::SeqCst);
+        let _ = self.control.send(Control::Stop);
+        self.shared.camera.lock().unwrap()
+    }
+
+    /// Ends the tour and hands the camera back.
+    pub fn stop(mut self) -> Camera {
+        self.shutdown();
+
+        let shared = self.shared.clone();
+        drop(self);
+
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => shared.camera.into_inner().unwrap(),
+            Err(_) => unreachable!("the tour thread has exited"),
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.shared.taken_over.store(true, Ordering::SeqCst);
+        let _ = self.control.send(Control::Stop);
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("tour thread panicked");
+        }
+    }
+}
+
+impl Drop for Tour {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Shared {
+    fn run(&self, stops: &[TourStop], order: TourOrder, control: Receiver<Control>) {
+        let mut rng = Rng::from_time();
+        let mut paused = false;
+
+        while !stops.is_empty() {
+            let mut sequence: Vec<usize> = (0..stops.len()).collect();
+            if order == TourOrder::Shuffled {
+                rng.shuffle(&mut sequence);
+            }
+
+            for i in sequence {
+                {
+                    let mut camera = self.camera.lock().unwrap();
+                    if self.taken_over.load(Ordering::SeqCst) {
+                        return;
+                    }
+
+                    *self.current.lock().unwrap() = Some(i);
+                    let result = go(&mut camera, stops[i].shot);
+                    *self.error.lock().unwrap() = result.err().map(|err| err.to_string());
+                }
+
+                if !dwell(stops[i].dwell, &control, &mut paused) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn go(camera: &mut Camera, shot: Shot) -> Result<()> {
+    match shot {
+        Shot::Preset { num, speed } => perform(camera, Action::Recall { preset: num, speed }),
+        Shot::Position {
+            pan_tilt,
+            zoom,
+            pan_speed,
+            tilt_speed,
+        } => {
+            let speed = Some((pan_speed, tilt_speed));
+            perform(camera, Action::Move { pan_tilt, speed })?;
+            perform(
+                camera,
+                Action::Zoom {
+                    position: zoom,
+                    speed: None,
+                },
+            )
+        }
+    }
+}
+
+/// Waits out a stop's dwell time, returning `false` if the tour should end.
+fn dwell(dwell: Duration, control: &Receiver<Control>, paused: &mut bool) -> bool {
+    let mut left = dwell;
+
+    loop {
+        let started = Instant::now();
+        let received = if *paused {
+            control.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            control.recv_timeout(left)
+        };
+        if !*paused {
+            left = left.saturating_sub(started.elapsed());
+        }
+
+        match received {
+            Ok(Control::Pause) => *paused = true,
+            Ok(Control::Resume) => *paused = false,
+            Ok(Control::Skip) | Err(RecvTimeoutError::Timeout) => return true,
+            Ok(Control::Stop) | Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+/// A small xorshift generator, which is plenty for shuffling shots.
+struct Rng(u64);
+
+impl Rng {
+    fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Rng(nanos | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::camera;
+
+    const DWELL: Duration = Duration::from_millis(100);
+
+    fn preset(num: u8, dwell: Duration) -> TourStop {
+        TourStop {
+            shot: Shot::Preset { num, speed: None },
+            dwell,
+        }
+    }
+
+    fn wait_until(mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_cycles_through_stops() {
+        let tour = Tour::start(
+            camera(),
+            vec![preset(1, DWELL), preset(2, DWELL)],
+            TourOrder::Sequential,
+        );
+
+        wait_until(|| tour.current() == Some(1));
+        wait_until(|| tour.current() == Some(0));
+        assert!(tour.is_running());
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let tour = Tour::start(
+            camera(),
+            vec![preset(1, DWELL), preset(2, DWELL)],
+            TourOrder::Sequential,
+        );
+        wait_until(|| tour.current().is_some());
+
+        tour.pause();
+        let paused_at = tour.current();
+        thread::sleep(DWELL * 3);
+        assert_eq!(tour.current(), paused_at);
+        assert!(tour.is_paused());
+
+        tour.resume();
+        wait_until(|| tour.current() != paused_at);
+    }
+
+    #[test]
+    fn test_skip() {
+        let long = Duration::from_secs(60);
+        let tour = Tour::start(
+            camera(),
+            vec![preset(1, long), preset(2, long)],
+            TourOrder::Sequential,
+        );
+        wait_until(|| tour.current() == Some(0));
+
+        tour.skip();
+        wait_until(|| tour.current() == Some(1));
+    }
+
+    #[test]
+    fn test_manual_control_ends_tour() {
+        let stop = TourStop {
+            shot: Shot::Position {
+                pan_tilt: PanTiltValue { pan: 0, tilt: 0 },
+                zoom: 0,
+                pan_speed: 0x18,
+                tilt_speed: 0x14,
+            },
+            dwell: DWELL,
+        };
+        let tour = Tour::start(camera(), vec![stop], TourOrder::Sequential);
+        wait_until(|| tour.current().is_some());
+
+        tour.camera().zoom().set(0x1000).unwrap();
+        wait_until(|| !tour.is_running());
+
+        let mut camera = tour.stop();
+        let zoom = camera
+            .zoom()
+            .wait_until_stopped(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(zoom.position, 0x1000);
+    }
+
+    #[test]
+    fn test_unreachable_stop() {
+        let stop = |pan_speed| TourStop {
+            shot: Shot::Position {
+                pan_tilt: PanTiltValue { pan: 0, tilt: 0 },
+                zoom: 0,
+                pan_speed,
+                tilt_speed: 0x01,
+            },
+            dwell: Duration::from_secs(60),
+        };
+        // The simulator rejects a pan speed it doesn't have.
+        let tour = Tour::start(
+            camera(),
+            vec![stop(0x30), stop(0x01)],
+            TourOrder::Sequential,
+        );
+
+        wait_until(|| tour.error().is_some());
+        assert_eq!(tour.current(), Some(0));
+        assert_eq!(tour.error().unwrap(), "syntax error");
+        assert!(tour.is_running());
+
+        tour.skip();
+        wait_until(|| tour.current() == Some(1) && tour.error().is_none());
+    }
+
+    #[test]
+    fn test_no_stops() {
+        let tour = Tour::start(camera(), vec![], TourOrder::Shuffled);
+        wait_until(|| !tour.is_running());
+        assert_eq!(tour.current(), None);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        let mut items: Vec<usize> = (0..8).collect();
+        rng.shuffle(&mut items);
+
+        assert_ne!(items, (0..8).collect::<Vec<_>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..8).collect::<Vec<_>>());
+    }
+}
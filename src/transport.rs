@@ -0,0 +1,41 @@
+use serialport::prelude::*;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+/// A byte channel to one or more cameras, such as a serial port or a network socket.
+///
+/// `send` is always given a single complete VISCA packet, while `recv` may return any
+/// number of bytes: `Interface` takes care of splitting them into replies.
+pub trait Transport: Send {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()>;
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Limits how long `recv` blocks waiting for data, for transports that support it.
+    fn set_read_timeout(&mut self, _timeout: Duration) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct SerialTransport {
+    port: Box<dyn SerialPort>,
+}
+
+impl SerialTransport {
+    pub fn new(port: Box<dyn SerialPort>) -> Self {
+        SerialTransport { port }
+    }
+}
+
+impl Transport for SerialTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.port.write_all(packet)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.port.read(buf)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port.set_timeout(timeout).map_err(Into::into)
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{Error, Result};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the position is polled while waiting for the camera to settle.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long the position must stay the same for the camera to count as stopped. Cameras
+/// take a moment to start moving after a command, so this can't be too short.
+pub const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Where a camera ended up after a wait, and how long it took to get there.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Settled<T> {
+    pub position: T,
+    pub elapsed: Duration,
+    /// Whether the camera got to the position it was waited for. This is `false` if it
+    /// stopped short, or never started moving, and always is when waiting for it to stop.
+    pub reached: bool,
+}
+
+/// Polls `read` until `done` accepts the position or it stops changing, whichever comes
+/// first, failing with `Error::Timeout` if neither happens within `timeout`.
+pub(crate) fn settle<T, F, D>(mut read: F, done: D, timeout: Duration) -> Result<Settled<T>>
+where
+    T: Copy + PartialEq,
+    F: FnMut() -> Result<T>,
+    D: Fn(T) -> bool,
+{
+    let start = Instant::now();
+    let mut position = read()?;
+    let mut still_since = start;
+
+    loop {
+        let reached = done(position);
+        if reached || still_since.elapsed() >= SETTLE_TIME {
+            return Ok(Settled {
+                position,
+                elapsed: start.elapsed(),
+                reached,
+            });
+        }
+        if start.elapsed() >= timeout {
+            return Err(Error::Timeout);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+
+        let next = read()?;
+        if next != position {
+            position = next;
+            still_since = Instant::now();
+        }
+    }
+}
+
+/// Polls `read` until `done` accepts the position, failing with `Error::Timeout` if it
+/// doesn't within `timeout`. Unlike `settle`, a position that stays put doesn't end the wait.
+pub(crate) fn wait_until<T, F, D>(mut read: F, done: D, timeout: Duration) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+    D: Fn(&T) -> bool,
+{
+    let start = Instant::now();
+    loop {
+        let position = read()?;
+        if done(&position) {
+            return Ok(position);
+        }
+        if start.elapsed() >= timeout {
+            return Err(Error::Timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+pub(crate) fn within<T: Into<i32>>(position: T, target: T, tolerance: u16) -> bool {
+    (position.into() - target.into()).abs() <= tolerance as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::PanTiltValue;
+    use crate::sim::Simulator;
+    use crate::testing::{camera, camera_with};
+
+    #[test]
+    fn test_wait_for_zoom() {
+        let mut camera = camera();
+        camera.zoom().set(0x800).unwrap();
+
+        let settled = camera
+            .zoom()
+            .wait_for_position(0x800, 0, Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(settled.position, 0x800);
+        assert!(settled.reached);
+        assert!(settled.elapsed > Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_wait_for_pan_tilt() {
+        let mut sim = Simulator::new();
+        sim.set_pan_tilt(PanTiltValue { pan: 90, tilt: 45 });
+        let mut camera = camera_with(sim);
+
+        let target = PanTiltValue { pan: 100, tilt: 50 };
+        camera.pan_tilt().set_absolute(target).unwrap();
+
+        let settled = camera
+            .pan_tilt()
+            .wait_for_position(target, 2, Duration::from_secs(2))
+            .unwrap();
+        assert!((settled.position.pan - 100).abs() <= 2);
+        assert!((settled.position.tilt - 50).abs() <= 2);
+    }
+
+    #[test]
+    fn test_stops_short_of_target() {
+        let mut sim = Simulator::new();
+        sim.set_zoom(0x3f00);
+        let mut camera = camera_with(sim);
+
+        // The lens stops at its tele end, well short of the target.
+        camera.zoom().set(0x5000).unwrap();
+        let settled = camera
+            .zoom()
+            .wait_for_position(0x5000, 0, Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(settled.position, 0x4000);
+        assert!(!settled.reached);
+        assert!(settled.elapsed >= SETTLE_TIME);
+    }
+
+    #[test]
+    fn test_wait_until_stopped() {
+        let mut camera = camera();
+        camera.focus().set(0x2000).unwrap();
+
+        let settled = camera
+            .focus()
+            .wait_until_stopped(Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(settled.position, 0x2000);
+    }
+
+    #[test]
+    fn test_never_starts_moving() {
+        let mut camera = camera();
+
+        // Nothing was sent, so the zoom stays where it is.
+        let settled = camera
+            .zoom()
+            .wait_for_position(0x800, 0, Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(settled.position, 0);
+        assert!(!settled.reached);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut camera = camera();
+        camera.zoom().set(0x4000).unwrap();
+
+        match camera
+            .zoom()
+            .wait_for_position(0x4000, 0, Duration::from_millis(100))
+        {
+            Err(Error::Timeout) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use visca::{Camera, Error, ErrorKind, PanTiltValue, ReplayTransport};
+
+fn replay(path: &str) -> Camera {
+    let path = format!("{}/tests/captures/{}", env!("CARGO_MANIFEST_DIR"), path);
+    Camera::with_transport(ReplayTransport::open(path).unwrap())
+}
+
+#[test]
+fn test_replay_session() {
+    let mut camera = replay("session.cap");
+
+    let start = camera.pan_tilt().get().unwrap();
+    assert_eq!(
+        start,
+        PanTiltValue {
+            pan: 0x50,
+            tilt: 0x32
+        }
+    );
+
+    camera.pan_tilt().set_absolute(start).unwrap();
+    camera.pan_tilt().up().unwrap();
+    camera.pan_tilt().stop().unwrap();
+
+    camera.zoom().set(0x1bc0).unwrap();
+    assert_eq!(camera.zoom().get().unwrap(), 0x1bc0);
+
+    camera.presets().set(3).unwrap();
+    camera.presets().recall(3).unwrap();
+
+    match camera.presets().recall(10) {
+        Err(Error::Camera(kind)) => assert_eq!(kind, ErrorKind::Syntax),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+#[should_panic(expected = "but the capture expected")]
+fn test_replay_mismatch() {
+    let mut camera = replay("session.cap");
+    let _ = camera.zoom().get();
+}
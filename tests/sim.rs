@@ -0,0 +1,79 @@
+#![cfg(target_os = "linux")]
+
+use std::thread;
+use std::time::Duration;
+use visca::{
+    Camera, Error, ErrorKind, Fault, FaultSchedule, PanTiltValue, Simulator, VirtualSerialPort,
+};
+
+fn simulated_camera(faults: FaultSchedule) -> Camera {
+    let mut port = VirtualSerialPort::open().unwrap();
+    let path = port.path().to_path_buf();
+
+    thread::spawn(move || {
+        let mut sim = Simulator::new();
+        sim.set_faults(faults);
+        port.serve(&mut sim)
+    });
+
+    Camera::open(&path).unwrap()
+}
+
+#[test]
+fn test_camera_over_virtual_serial_port() {
+    let mut camera = simulated_camera(FaultSchedule::new());
+    assert_eq!(
+        camera.pan_tilt().get().unwrap(),
+        PanTiltValue { pan: 0, tilt: 0 }
+    );
+
+    camera.zoom().set(0x1000).unwrap();
+    assert!(camera.power().get().unwrap());
+    assert_eq!(camera.version().unwrap().max_socket, 2);
+}
+
+#[test]
+fn test_recovers_from_line_faults() {
+    let faults = FaultSchedule::new()
+        .at(0, Fault::DropAck)
+        .at(1, Fault::Corrupt)
+        .at(2, Fault::NetworkChange)
+        .at(3, Fault::SplitFrames)
+        .at(4, Fault::DelayCompletion(Duration::from_millis(200)));
+
+    let mut camera = simulated_camera(faults);
+    for _ in 0..5 {
+        camera.presets().recall(1).unwrap();
+    }
+    assert_eq!(camera.zoom().get().unwrap(), 0);
+}
+
+#[test]
+fn test_camera_errors() {
+    let faults = FaultSchedule::new()
+        .at(0, Fault::Error(ErrorKind::FullBuffer))
+        .at(1, Fault::Error(ErrorKind::NotExecutable));
+
+    let mut camera = simulated_camera(faults);
+
+    match camera.pan_tilt().home() {
+        Err(Error::Camera(kind)) => assert_eq!(kind, ErrorKind::FullBuffer),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match camera.pan_tilt().home() {
+        Err(Error::Camera(kind)) => assert_eq!(kind, ErrorKind::NotExecutable),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    camera.pan_tilt().home().unwrap();
+}
+
+#[test]
+fn test_silent_camera_times_out() {
+    let mut camera = simulated_camera(FaultSchedule::new().from(1, Fault::Silence));
+    camera.pan_tilt().home().unwrap();
+
+    match camera.pan_tilt().home() {
+        Err(Error::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::TimedOut),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}